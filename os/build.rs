@@ -7,6 +7,7 @@ const USER_IMG: &str = "../usr/build/riscv64.img";
 
 fn main() {
     println!("cargo:rerun-if-env-changed=USER_IMG");
+    println!("cargo:rerun-if-env-changed=SCHEDULER");
     println!("cargo:rerun-if-changed={}", USER_IMG);
     gen_link_user_asm().unwrap();
 }
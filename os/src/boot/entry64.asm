@@ -1,6 +1,11 @@
     .section .text.entry
     .globl _start
 _start:
+    # a0 为 hart id，内核中一直保存在 tp 中
+    mv      tp, a0
+    li      t0, 8
+    bgeu    a0, t0, park
+
     lui     t0, %hi(boot_page_table_sv39)
     li      t1, 0xffffffffc0000000 - 0x80000000
     sub     t0, t0, t1
@@ -10,17 +15,31 @@ _start:
     csrw    satp, t0
     sfence.vma
 
+    # 每个 hart 使用各自的启动栈
     lui sp, %hi(bootstacktop)
+    li t0, 4096 * 4
+    mul t0, t0, a0
+    sub sp, sp, t0
 
+    # 0 号 hart 进行初始化，其他 hart 等待它完成
+    bnez a0, 1f
     lui t0, %hi(rust_main)
     addi t0, t0, %lo(rust_main)
     jr t0
+1:
+    lui t0, %hi(others_main)
+    addi t0, t0, %lo(others_main)
+    jr t0
+
+park:
+    wfi
+    j park
 
     .section .bss.stack
     .align 12
     .global bootstack
 bootstack:
-    .space 4096 * 4
+    .space 4096 * 4 * 8
     .global bootstacktop
 bootstacktop:
 
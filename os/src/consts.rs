@@ -18,3 +18,12 @@ pub const USER_STACK_SIZE: usize = 0x80000;
 pub const USER_STACK_OFFSET: usize = 0xffffffff00000000;
 
 pub const NOFILE: usize = 16;
+
+// 支持的 hart 数量，更多的 hart 在启动时直接停住，与 entry64.asm 一致
+pub const MAX_HARTS: usize = 8;
+
+// 系统调用中字符串参数的最大长度
+pub const USER_STR_MAX: usize = 4096;
+
+// exec 的参数与环境变量在用户栈上占用的总大小上限，包括指针数组
+pub const ARG_MAX: usize = USER_STACK_SIZE / 4;
@@ -29,6 +29,11 @@ impl Context {
         Context { content_addr: 0 }
     }
 
+    /// The page table the thread runs on once switched in, it must not be running now.
+    pub fn satp(&self) -> usize {
+        unsafe { (*(self.content_addr as *const ContextContent)).satp }
+    }
+
     pub unsafe fn new_kernel_thread(entry: usize, kstack_top: usize, satp: usize) -> Context {
         ContextContent::new_kernel_thread(entry, kstack_top, satp).push_at(kstack_top)
     }
@@ -45,8 +50,9 @@ impl Context {
         ustack_top: usize,
         kstack_top: usize,
         satp: usize,
+        args: [usize; 3],
     ) -> Self {
-        ContextContent::new_user_thread(entry, ustack_top, satp).push_at(kstack_top)
+        ContextContent::new_user_thread(entry, ustack_top, satp, args).push_at(kstack_top)
     }
 
     pub unsafe fn new_fork(tf: &TrapFrame, kstack_top: usize, satp: usize) -> Context {
@@ -86,7 +92,7 @@ impl ContextContent {
         content
     }
 
-    fn new_user_thread(entry: usize, ustack_top: usize, satp: usize) -> Self {
+    fn new_user_thread(entry: usize, ustack_top: usize, satp: usize, args: [usize; 3]) -> Self {
         ContextContent {
             ra: __trapret as usize,
             satp,
@@ -94,6 +100,10 @@ impl ContextContent {
             tf: {
                 let mut tf: TrapFrame = unsafe { zeroed() };
                 tf.x[2] = ustack_top;
+                // a0, a1, a2
+                tf.x[10] = args[0];
+                tf.x[11] = args[1];
+                tf.x[12] = args[2];
                 tf.sepc = entry;
                 tf.sstatus = sstatus::read();
                 tf.sstatus.set_spie(true);
@@ -1,16 +1,19 @@
-use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 
-use rcore_fs::vfs::INode;
-use spin::Mutex;
-
+use rcore_fs::vfs::{INode, Result};
+use crate::fs::pipe::PipeEnd;
 use crate::fs::ROOT_INODE;
 
+// exec 时关闭该文件描述符
+pub const O_CLOEXEC: i32 = 0x80000;
+
 #[derive(Copy, Clone, Debug)]
 pub enum FileDescriptorType {
     FdNone,
     FdInode,
     FdPipe,
+    FdStdin,
+    FdStdout,
     // FdDevice,
 }
 
@@ -19,8 +22,9 @@ pub struct File {
     fdtype: FileDescriptorType,
     readable: bool,
     writable: bool,
+    cloexec: bool,
     pub inode: Option<Arc<dyn INode>>,
-    pub pipe: Option<Arc<Mutex<VecDeque<u8>>>>,
+    pub pipe: Option<PipeEnd>,
     offset: usize,
 }
 
@@ -30,6 +34,7 @@ impl File {
             fdtype: FileDescriptorType::FdNone,
             readable: false,
             writable: false,
+            cloexec: false,
             inode: None,
             pipe: None,
             offset: 0,
@@ -47,6 +52,12 @@ impl File {
     pub fn get_writable(&self) -> bool {
         self.writable
     }
+    pub fn set_cloexec(&mut self, v: bool) {
+        self.cloexec = v;
+    }
+    pub fn get_cloexec(&self) -> bool {
+        self.cloexec
+    }
     pub fn set_fdtype(&mut self, t: FileDescriptorType) {
         self.fdtype = t;
     }
@@ -60,7 +71,8 @@ impl File {
         self.offset
     }
 
-    pub fn open_file(&mut self, path: &'static str, flags: i32) {
+    pub fn open_file(&mut self, path: &str, flags: i32) -> Result<()> {
+        let inode = ROOT_INODE.lookup(path)?;
         self.set_fdtype(FileDescriptorType::FdInode);
         self.set_readable(true);
         if (flags & 1) > 0 {
@@ -69,14 +81,30 @@ impl File {
         if (flags & 3) > 0 {
             self.set_writable(true);
         }
-        self.inode = Some(ROOT_INODE.lookup(path).unwrap().clone());
+        self.set_cloexec((flags & O_CLOEXEC) != 0);
+        self.inode = Some(inode);
         self.set_offset(0);
+        Ok(())
     }
 
-    pub fn open_pipe(&mut self, pipe: Arc<Mutex<VecDeque<u8>>>) {
-        self.set_fdtype(FileDescriptorType::FdPipe);
+    pub fn open_stdin(&mut self) {
+        self.set_fdtype(FileDescriptorType::FdStdin);
         self.set_readable(true);
+        self.set_writable(false);
+    }
+
+    // 标准输出与标准错误输出都写到串口
+    pub fn open_stdout(&mut self) {
+        self.set_fdtype(FileDescriptorType::FdStdout);
+        self.set_readable(false);
         self.set_writable(true);
-        self.pipe = Some(pipe);
+    }
+
+    // 管道的读端只读，写端只写
+    pub fn open_pipe(&mut self, end: PipeEnd) {
+        self.set_fdtype(FileDescriptorType::FdPipe);
+        self.set_readable(!end.is_write());
+        self.set_writable(end.is_write());
+        self.pipe = Some(end);
     }
 }
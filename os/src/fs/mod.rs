@@ -9,6 +9,8 @@ use crate::consts::PAGE_SIZE;
 
 mod device;
 pub mod file;
+pub mod pipe;
+pub mod poll;
 pub mod stdio;
 
 lazy_static! {
@@ -0,0 +1,202 @@
+use alloc::sync::Arc;
+
+use spin::{Mutex, MutexGuard};
+
+use crate::fs::poll::{POLLERR, POLLHUP, POLLIN, POLLOUT};
+use crate::process::signal::interrupted;
+use crate::process::thread_pool::WakeupHandle;
+use crate::sync::condvar::*;
+use crate::syscall::SysError;
+
+// 管道缓冲区的大小
+pub const PIPE_SIZE: usize = 4096;
+
+struct PipeBuffer {
+    data: [u8; PIPE_SIZE],
+    head: usize,
+    len: usize,
+    // 读端与写端各自被打开的次数
+    readers: usize,
+    writers: usize,
+}
+
+pub struct Pipe {
+    buf: Mutex<PipeBuffer>,
+    // 有数据可读，或写端全部关闭
+    readable: Condvar,
+    // 有空间可写，或读端全部关闭
+    writable: Condvar,
+}
+
+/// One end of a pipe held by a `File`, the pipe counts how many ends are still open.
+pub struct PipeEnd {
+    pub pipe: Arc<Pipe>,
+    write: bool,
+}
+
+impl Pipe {
+    /// Create a pipe, returns its read end and write end.
+    pub fn new() -> (PipeEnd, PipeEnd) {
+        let pipe = Arc::new(Pipe {
+            buf: Mutex::new(PipeBuffer {
+                data: [0; PIPE_SIZE],
+                head: 0,
+                len: 0,
+                readers: 0,
+                writers: 0,
+            }),
+            readable: Condvar::new(),
+            writable: Condvar::new(),
+        });
+        (PipeEnd::new(pipe.clone(), false), PipeEnd::new(pipe, true))
+    }
+
+    /// Read at most `buf.len()` bytes, blocking while the pipe is empty.
+    ///
+    /// Returns 0 at EOF, that is when the pipe is empty and all the write ends are closed.
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
+        loop {
+            let mut pipe = self.buf.lock();
+            if pipe.len > 0 {
+                let n = buf.len().min(pipe.len);
+                for i in 0..n {
+                    buf[i] = pipe.data[(pipe.head + i) % PIPE_SIZE];
+                }
+                pipe.head = (pipe.head + n) % PIPE_SIZE;
+                pipe.len -= n;
+                drop(pipe);
+                self.writable.notify_all();
+                return Ok(n);
+            }
+            if pipe.writers == 0 {
+                return Ok(0);
+            }
+            wait_or_interrupted(&self.readable, pipe)?;
+        }
+    }
+
+    /// Write all of `buf`, blocking while the pipe is full.
+    ///
+    /// Fails with EPIPE if all the read ends are closed before anything is written.
+    pub fn write(&self, buf: &[u8]) -> Result<usize, SysError> {
+        let mut written = 0;
+        while written < buf.len() {
+            let mut pipe = self.buf.lock();
+            if pipe.readers == 0 {
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(SysError::EPIPE)
+                };
+            }
+            if pipe.len < PIPE_SIZE {
+                let n = (buf.len() - written).min(PIPE_SIZE - pipe.len);
+                for i in 0..n {
+                    let tail = (pipe.head + pipe.len + i) % PIPE_SIZE;
+                    pipe.data[tail] = buf[written + i];
+                }
+                pipe.len += n;
+                written += n;
+                drop(pipe);
+                self.readable.notify_all();
+                continue;
+            }
+            if let Err(error) = wait_or_interrupted(&self.writable, pipe) {
+                return if written > 0 { Ok(written) } else { Err(error) };
+            }
+        }
+        Ok(written)
+    }
+
+    /// The poll events of the read end, or of the write end if `write`.
+    pub fn poll(&self, write: bool) -> i16 {
+        let pipe = self.buf.lock();
+        let mut events = 0;
+        if write {
+            if pipe.len < PIPE_SIZE {
+                events |= POLLOUT;
+            }
+            if pipe.readers == 0 {
+                events |= POLLERR;
+            }
+        } else {
+            if pipe.len > 0 {
+                events |= POLLIN;
+            }
+            if pipe.writers == 0 {
+                events |= POLLHUP;
+            }
+        }
+        events
+    }
+
+    // 读端等待有数据可读，写端等待有空间可写
+    fn condvar(&self, write: bool) -> &Condvar {
+        if write {
+            &self.writable
+        } else {
+            &self.readable
+        }
+    }
+
+    /// Wake up `handle` when the end becomes ready, for poll.
+    pub fn register(&self, write: bool, handle: WakeupHandle) {
+        self.condvar(write).register(handle);
+    }
+
+    pub fn unregister(&self, write: bool, handle: WakeupHandle) {
+        self.condvar(write).unregister(handle);
+    }
+}
+
+// 被杀死或收到信号的进程不再等待管道，等待中收到信号时会被唤醒
+fn wait_or_interrupted(condvar: &Condvar, guard: MutexGuard<PipeBuffer>) -> Result<(), SysError> {
+    if interrupted() {
+        return Err(SysError::EINTR);
+    }
+    condvar.wait_unlock(guard);
+    if interrupted() {
+        return Err(SysError::EINTR);
+    }
+    Ok(())
+}
+
+impl PipeEnd {
+    fn new(pipe: Arc<Pipe>, write: bool) -> Self {
+        {
+            let mut buf = pipe.buf.lock();
+            if write {
+                buf.writers += 1;
+            } else {
+                buf.readers += 1;
+            }
+        }
+        PipeEnd { pipe, write }
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.write
+    }
+}
+
+impl Clone for PipeEnd {
+    fn clone(&self) -> Self {
+        PipeEnd::new(self.pipe.clone(), self.write)
+    }
+}
+
+impl Drop for PipeEnd {
+    fn drop(&mut self) {
+        let mut buf = self.pipe.buf.lock();
+        // 唤醒另一端等待的线程，让它们看到 EOF 或 EPIPE
+        if self.write {
+            buf.writers -= 1;
+            drop(buf);
+            self.pipe.readable.notify_all();
+        } else {
+            buf.readers -= 1;
+            drop(buf);
+            self.pipe.writable.notify_all();
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Wait for several files at once, as ppoll.
+//!
+//! Only the console and pipes may block, the other files are always ready.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::fs::file::FileDescriptorType;
+use crate::fs::pipe::Pipe;
+use crate::fs::stdio::STDIN;
+use crate::process::signal::interrupted;
+use crate::process::thread_pool::WakeupHandle;
+use crate::process::{add_timer, cancel_timer, current_handle, current_process, park, wake_up};
+use crate::syscall::SysError;
+use crate::timer::get_cycle;
+
+// poll 的事件，与 Linux 一致
+pub const POLLIN: i16 = 0x1;
+pub const POLLOUT: i16 = 0x4;
+pub const POLLERR: i16 = 0x8;
+pub const POLLHUP: i16 = 0x10;
+pub const POLLNVAL: i16 = 0x20;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+// 被等待的文件
+enum Source {
+    Stdin,
+    Pipe(Arc<Pipe>, bool),
+    Always(i16),
+    Invalid,
+    // fd 为负数时忽略
+    Ignored,
+}
+
+impl Source {
+    fn new(fd: i32) -> Self {
+        if fd < 0 {
+            return Source::Ignored;
+        }
+        let file = current_process()
+            .lock()
+            .ofile
+            .get(fd as usize)
+            .cloned()
+            .flatten();
+        let file = match file {
+            Some(file) => file,
+            None => return Source::Invalid,
+        };
+        let file = file.lock();
+        match file.get_fdtype() {
+            FileDescriptorType::FdStdin => Source::Stdin,
+            FileDescriptorType::FdStdout => Source::Always(POLLOUT),
+            FileDescriptorType::FdPipe => {
+                let end = file.pipe.as_ref().unwrap();
+                Source::Pipe(end.pipe.clone(), end.is_write())
+            }
+            FileDescriptorType::FdInode => {
+                let mut events = 0;
+                if file.get_readable() {
+                    events |= POLLIN;
+                }
+                if file.get_writable() {
+                    events |= POLLOUT;
+                }
+                Source::Always(events)
+            }
+            _ => Source::Invalid,
+        }
+    }
+
+    fn events(&self) -> i16 {
+        match self {
+            Source::Stdin if STDIN.poll() => POLLIN,
+            Source::Stdin => 0,
+            Source::Pipe(pipe, write) => pipe.poll(*write),
+            Source::Always(events) => *events,
+            Source::Invalid => POLLNVAL,
+            Source::Ignored => 0,
+        }
+    }
+
+    fn register(&self, handle: WakeupHandle) {
+        match self {
+            Source::Stdin => STDIN.register(handle),
+            Source::Pipe(pipe, write) => pipe.register(*write, handle),
+            _ => {}
+        }
+    }
+
+    fn unregister(&self, handle: WakeupHandle) {
+        match self {
+            Source::Stdin => STDIN.unregister(handle),
+            Source::Pipe(pipe, write) => pipe.unregister(*write, handle),
+            _ => {}
+        }
+    }
+}
+
+/// Wait until one of `fds` is ready or `deadline` in timer cycles passes, forever if `None`.
+///
+/// Fills in the `revents` and returns how many files are ready, 0 if timed out.
+/// Fails with EINTR if a signal arrives first.
+pub fn poll(fds: &mut [PollFd], deadline: Option<u64>) -> Result<usize, SysError> {
+    let sources: Vec<Source> = fds.iter().map(|pfd| Source::new(pfd.fd)).collect();
+    let handle = current_handle();
+    let timer = deadline.map(|deadline| add_timer(deadline, move || wake_up(handle)));
+    let result = loop {
+        // 先排队再检查，检查之后的通知也能唤醒当前线程
+        for source in sources.iter() {
+            source.register(handle);
+        }
+        let mut ready = 0;
+        for (pfd, source) in fds.iter_mut().zip(sources.iter()) {
+            // 错误与挂断总是报告
+            pfd.revents = source.events() & (pfd.events | POLLERR | POLLHUP | POLLNVAL);
+            if pfd.revents != 0 {
+                ready += 1;
+            }
+        }
+        let timed_out = deadline.map_or(false, |deadline| get_cycle() >= deadline);
+        if ready == 0 && !timed_out && !interrupted() {
+            park();
+        }
+        for source in sources.iter() {
+            source.unregister(handle);
+        }
+        if ready > 0 || timed_out {
+            break Ok(ready);
+        }
+        if interrupted() {
+            break Err(SysError::EINTR);
+        }
+    };
+    // 提前返回时取消定时器，以免它以后多唤醒一次
+    if let Some(timer) = timer {
+        cancel_timer(timer);
+    }
+    result
+}
@@ -3,11 +3,26 @@ use alloc::{collections::VecDeque, sync::Arc};
 use lazy_static::*;
 use spin::Mutex;
 
+use crate::process::current_process;
+use crate::process::session::group_in_session;
+use crate::process::signal::{interrupted, send_group, SIGCONT, SIGHUP, SIGINT, SIGTSTP, SIGTTIN};
+use crate::process::thread_pool::WakeupHandle;
+use crate::process::Tid;
 use crate::sync::condvar::*;
+use crate::syscall::SysError;
+
+/// The session whose controlling terminal is the console
+#[derive(Copy, Clone, Default)]
+struct Owner {
+    session: Option<Tid>,
+    // 前台进程组，控制台的 Ctrl-C 与 Ctrl-Z 发往这个组
+    foreground: Option<Tid>,
+}
 
 pub struct Stdin {
     buf: Mutex<VecDeque<char>>,
     pushed: Condvar,
+    owner: Mutex<Owner>,
 }
 
 impl Stdin {
@@ -15,25 +30,159 @@ impl Stdin {
         Stdin {
             buf: Mutex::new(VecDeque::new()),
             pushed: Condvar::new(),
+            owner: Mutex::new(Owner::default()),
+        }
+    }
+
+    pub fn foreground(&self) -> Option<Tid> {
+        self.owner.lock().foreground
+    }
+
+    /// Make the console the controlling terminal of session `sid`, with foreground group `pgid`.
+    pub fn attach(&self, sid: Tid, pgid: Tid) {
+        *self.owner.lock() = Owner {
+            session: Some(sid),
+            foreground: Some(pgid),
+        };
+    }
+
+    // 当前进程的进程组与会话，不属于拥有控制台的会话时返回 ENOTTY
+    fn check_session(&self) -> Result<(Tid, Tid), SysError> {
+        let (pgid, sid) = {
+            let proc = current_process().lock();
+            (proc.pgid, proc.sid)
+        };
+        if self.owner.lock().session == Some(sid) {
+            Ok((pgid, sid))
+        } else {
+            Err(SysError::ENOTTY)
+        }
+    }
+
+    /// The foreground group, as tcgetpgrp.
+    pub fn get_pgrp(&self) -> Result<Tid, SysError> {
+        self.check_session()?;
+        self.foreground().ok_or(SysError::ENOTTY)
+    }
+
+    /// Set the foreground group to `pgid`, a group in the session owning the console, as tcsetpgrp.
+    pub fn set_pgrp(&self, pgid: Tid) -> Result<(), SysError> {
+        let (_, sid) = self.check_session()?;
+        if !group_in_session(pgid, sid) {
+            return Err(SysError::EPERM);
+        }
+        self.owner.lock().foreground = Some(pgid);
+        Ok(())
+    }
+
+    /// Make the console the controlling terminal of the current session, as TIOCSCTTY.
+    ///
+    /// Only a session leader may do it, when the console is not owned by another session.
+    pub fn set_ctty(&self) -> Result<(), SysError> {
+        let (pid, pgid, sid) = {
+            let proc = current_process().lock();
+            (proc.pid, proc.pgid, proc.sid)
+        };
+        let mut owner = self.owner.lock();
+        if pid != sid || owner.session.map_or(false, |session| session != sid) {
+            return Err(SysError::EPERM);
+        }
+        *owner = Owner {
+            session: Some(sid),
+            foreground: Some(pgid),
+        };
+        Ok(())
+    }
+
+    /// The leader of session `sid` exits, the console hangs up if the session owns it.
+    ///
+    /// The foreground group receives SIGHUP and SIGCONT as in Linux.
+    pub fn hangup(&self, sid: Tid) {
+        let foreground = {
+            let mut owner = self.owner.lock();
+            if owner.session != Some(sid) {
+                return;
+            }
+            let foreground = owner.foreground;
+            *owner = Owner::default();
+            foreground
+        };
+        if let Some(pgid) = foreground {
+            send_group(pgid, SIGHUP).ok();
+            send_group(pgid, SIGCONT).ok();
+        }
+    }
+
+    // 会话中的后台进程读控制台时，它的进程组收到 SIGTTIN 而停止
+    // 忽略或阻塞了 SIGTTIN 时返回 EIO
+    fn check_foreground(&self) -> Result<(), SysError> {
+        let (pgid, sid, discarded) = {
+            let proc = current_process().lock();
+            (proc.pgid, proc.sid, proc.signals.discarded(SIGTTIN))
+        };
+        let owner = *self.owner.lock();
+        if owner.session != Some(sid) || owner.foreground == Some(pgid) {
+            return Ok(());
+        }
+        if discarded {
+            return Err(SysError::EIO);
+        }
+        send_group(pgid, SIGTTIN).ok();
+        Err(SysError::EINTR)
+    }
+
+    /// Handle a character typed on the console, Ctrl-C and Ctrl-Z signal the foreground group.
+    pub fn receive(&self, ch: char) {
+        let sig = match ch {
+            '\x03' => SIGINT,
+            '\x1a' => SIGTSTP,
+            '\r' => return self.push('\n'),
+            _ => return self.push(ch),
+        };
+        // 与终端一样回显 ^C 或 ^Z
+        println!("^{}", (ch as u8 + b'@') as char);
+        if let Some(pgid) = self.foreground() {
+            send_group(pgid, sig).ok();
         }
     }
 
     pub fn push(&self, ch: char) {
         self.buf.lock().push_back(ch);
-        self.pushed.notify();
+        // 等待的可能还有 poll，全部唤醒以免通知被它取走
+        self.pushed.notify_all();
+    }
+
+    /// Whether there is input to read
+    pub fn poll(&self) -> bool {
+        !self.buf.lock().is_empty()
+    }
+
+    /// Wake up `handle` when input arrives, for poll.
+    pub fn register(&self, handle: WakeupHandle) {
+        self.pushed.register(handle);
     }
 
-    pub fn pop(&self) -> char {
+    pub fn unregister(&self, handle: WakeupHandle) {
+        self.pushed.unregister(handle);
+    }
+
+    // 缓冲区为空时阻塞，否则只取出已有的字符，返回读到的字节数
+    // 等待时收到信号返回 EINTR
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
         loop {
-            let ret = self.buf.lock().pop_front();
-            match ret {
-                Some(ch) => {
-                    return ch;
-                }
-                None => {
-                    self.pushed.wait();
+            self.check_foreground()?;
+            let mut queue = self.buf.lock();
+            if !queue.is_empty() {
+                let n = buf.len().min(queue.len());
+                for (dst, ch) in buf.iter_mut().zip(queue.drain(..n)) {
+                    *dst = ch as u8;
                 }
+                return Ok(n);
+            }
+            if interrupted() {
+                return Err(SysError::EINTR);
             }
+            self.pushed.wait_unlock(queue);
         }
     }
 }
@@ -16,6 +16,7 @@ pub extern "C" fn rust_main() -> ! {
     crate::fs::init();
     crate::process::init();
     crate::timer::init();
+    crate::smp::start_others();
     crate::process::run();
     loop {}
 }
@@ -1,13 +1,16 @@
 use riscv::register::sie;
 use riscv::register::{
     scause::{Exception, Interrupt, Trap},
-    sscratch, sstatus, stvec,
+    sscratch, sstatus,
+    sstatus::SPP,
+    stvec,
 };
 
 use crate::context::TrapFrame;
 use crate::memory::access_pa_via_va;
+use crate::process::signal::{do_signal, SIGBUS, SIGILL, SIGSEGV};
 use crate::process::tick;
-use crate::timer::clock_set_next_event;
+use crate::smp::{handle_ipi, hart_id, lock_flushing};
 
 global_asm!(include_str!("trap/trap.asm"));
 
@@ -23,6 +26,8 @@ pub fn init() {
 
         // enable external interrupt
         sie::set_sext();
+        // IPI 通过软件中断送达
+        sie::set_ssoft();
 
         // closed by OpenSBI, so we open them manually
         // see https://github.com/rcore-os/rCore/blob/54fddfbe1d402ac1fafd9d58a0bd4f6a8dd99ece/kernel/src/arch/riscv32/board/virt/mod.rs#L4
@@ -32,10 +37,43 @@ pub fn init() {
     println!("++++ setup interrupt! ++++");
 }
 
+/// Set up the interrupts of the other harts, the serial port itself is set up once by hart 0.
+pub fn init_other() {
+    unsafe {
+        extern "C" {
+            fn __alltraps();
+        }
+        sscratch::write(0);
+        stvec::write(__alltraps as usize, stvec::TrapMode::Direct);
+        sstatus::set_sie();
+        sie::set_sext();
+        sie::set_ssoft();
+        init_external_interrupt();
+    }
+}
+
+const SERIAL: u32 = 0xa;
+
+// 每个 hart 的 S 态有各自的 PLIC 上下文，编号为 2 * hart + 1
+fn plic_context() -> usize {
+    2 * hart_id() + 1
+}
+
 pub unsafe fn init_external_interrupt() {
-    let hart0_s_mode_interrupt_enables: *mut u32 = access_pa_via_va(0x0c00_2080) as *mut u32;
-    const SERIAL: u32 = 0xa;
-    hart0_s_mode_interrupt_enables.write_volatile(1 << SERIAL);
+    let s_mode_interrupt_enables: *mut u32 =
+        access_pa_via_va(0x0c00_2000 + 0x80 * plic_context()) as *mut u32;
+    s_mode_interrupt_enables.write_volatile(1 << SERIAL);
+}
+
+// 中断同时发往所有 hart，只有 claim 到它的 hart 处理
+fn plic_claim() -> u32 {
+    let claim = access_pa_via_va(0x0c20_0004 + 0x1000 * plic_context()) as *mut u32;
+    unsafe { claim.read_volatile() }
+}
+
+fn plic_complete(irq: u32) {
+    let complete = access_pa_via_va(0x0c20_0004 + 0x1000 * plic_context()) as *mut u32;
+    unsafe { complete.write_volatile(irq) }
 }
 
 pub unsafe fn enable_serial_interrupt() {
@@ -46,6 +84,10 @@ pub unsafe fn enable_serial_interrupt() {
 
 #[no_mangle]
 pub fn rust_trap(tf: &mut TrapFrame) {
+    // 用户态与内核态的时间分别计入当前线程
+    if from_user(tf) {
+        crate::process::current_thread_mut().usage.charge(true);
+    }
     match tf.scause.cause() {
         Trap::Exception(Exception::Breakpoint) => breakpoint(&mut tf.sepc),
         Trap::Interrupt(Interrupt::SupervisorTimer) => super_timer(),
@@ -53,29 +95,80 @@ pub fn rust_trap(tf: &mut TrapFrame) {
         Trap::Exception(Exception::LoadPageFault) => page_fault(tf),
         Trap::Exception(Exception::StorePageFault) => page_fault(tf),
         Trap::Exception(Exception::UserEnvCall) => syscall(tf),
-        Trap::Exception(Exception::IllegalInstruction) => {
-            println!("IllegalInstruction");
-            panic!("IllegalInstruction")
-        }
+        Trap::Exception(Exception::IllegalInstruction) => bad_trap(tf, SIGILL),
+        Trap::Exception(Exception::InstructionMisaligned) => bad_trap(tf, SIGBUS),
+        Trap::Exception(Exception::StoreMisaligned) => bad_trap(tf, SIGBUS),
+        Trap::Exception(_) => bad_trap(tf, SIGSEGV),
         Trap::Interrupt(Interrupt::SupervisorExternal) => external(),
-        _ => panic!("undefined trap!"),
+        Trap::Interrupt(Interrupt::SupervisorSoft) => handle_ipi(),
+        Trap::Interrupt(_) => kernel_panic(tf, "undefined trap!"),
+    }
+    // 被杀死的进程不再返回用户态，返回之前处理信号
+    if from_user(tf) {
+        crate::process::exit_if_killed();
+        do_signal(tf);
+        crate::process::current_thread_mut().usage.charge(false);
     }
 }
 
+fn from_user(tf: &TrapFrame) -> bool {
+    tf.sstatus.spp() == SPP::User
+}
+
+// 用户态的异常杀死当前进程，内核态的异常仍然 panic
+fn bad_trap(tf: &TrapFrame, sig: usize) {
+    if !from_user(tf) {
+        kernel_panic(tf, "unexpected exception in kernel!");
+    }
+    println!(
+        "process {} killed by signal {}: {:?} va = {:#x} instruction = {:#x}",
+        crate::process::current_pid(),
+        sig,
+        tf.scause.cause(),
+        tf.stval,
+        tf.sepc
+    );
+    crate::process::kill_current(sig);
+}
+
+fn kernel_panic(tf: &TrapFrame, msg: &str) -> ! {
+    println!(
+        "{:?} va = {:#x} instruction = {:#x} sstatus = {:#x}",
+        tf.scause.cause(),
+        tf.stval,
+        tf.sepc,
+        tf.sstatus.bits()
+    );
+    for i in (0..32).step_by(4) {
+        println!(
+            "x{:<2} {:#018x}  x{:<2} {:#018x}  x{:<2} {:#018x}  x{:<2} {:#018x}",
+            i,
+            tf.x[i],
+            i + 1,
+            tf.x[i + 1],
+            i + 2,
+            tf.x[i + 2],
+            i + 3,
+            tf.x[i + 3]
+        );
+    }
+    panic!("{}", msg);
+}
+
 fn breakpoint(sepc: &mut usize) {
     println!("a breakpoint set @0x{:x}", sepc);
     *sepc += 2;
 }
 
 fn super_timer() {
-    clock_set_next_event();
+    // 下一次时钟中断由 tick 设置
     tick();
 }
 fn page_fault(tf: &mut TrapFrame) {
     use crate::consts::PHYSICAL_MEMORY_OFFSET;
     use riscv::addr::{Page, VirtAddr};
     use riscv::paging::Mapper;
-    use riscv::paging::{PageTable, Rv39PageTable};
+    use riscv::paging::{PageTable, PageTableFlags as EF, Rv39PageTable};
     use riscv::register::satp;
     println!(
         "{:?} va = {:#x} instruction = {:#x}",
@@ -88,10 +181,16 @@ fn page_fault(tf: &mut TrapFrame) {
         unsafe { satp::read().frame().as_kernel_mut(PHYSICAL_MEMORY_OFFSET) };
     let mut pg_table = Rv39PageTable::new(root_table, PHYSICAL_MEMORY_OFFSET);
     let page = Page::of_addr(VirtAddr::new(tf.stval));
-    let entry = pg_table.ref_entry(page.clone()).unwrap();
-    crate::memory::page_replace::PAGE_REPLACE_HANDLER
-        .lock()
-        .do_pgfault(entry, tf.stval);
+    match pg_table.ref_entry(page.clone()) {
+        // 页表项存在但无效，说明页面已被换出，将其换入
+        Ok(entry) if !entry.is_unused() && !entry.flags().contains(EF::VALID) => {
+            // 换出页面的 hart 持有这个锁等待 TLB 击落，等锁时也要响应
+            lock_flushing(&crate::memory::page_replace::PAGE_REPLACE_HANDLER)
+                .do_pgfault(entry, tf.stval);
+        }
+        // 访问了未映射的地址，或违反了页面的权限
+        _ => bad_trap(tf, SIGSEGV),
+    }
 }
 
 fn syscall(tf: &mut TrapFrame) {
@@ -101,17 +200,19 @@ fn syscall(tf: &mut TrapFrame) {
 }
 
 fn external() {
-    let _ = try_serial();
+    let irq = plic_claim();
+    if irq == SERIAL {
+        while try_serial() {}
+    }
+    if irq != 0 {
+        plic_complete(irq);
+    }
 }
 
 fn try_serial() -> bool {
     match super::io::getchar_option() {
         Some(ch) => {
-            if ch == '\r' {
-                crate::fs::stdio::STDIN.push('\n');
-            } else {
-                crate::fs::stdio::STDIN.push(ch);
-            }
+            crate::fs::stdio::STDIN.receive(ch);
             true
         }
         None => false,
@@ -1,7 +1,12 @@
 use core::fmt::{self, Write};
 
+use spin::Mutex;
+
 use crate::sbi;
 
+// 多个 hart 同时输出时不让各自的内容交错
+static PRINT_LOCK: Mutex<()> = Mutex::new(());
+
 pub fn putchar(ch: char) {
     sbi::console_putchar(ch as u8 as usize);
 }
@@ -12,6 +17,13 @@ pub fn puts(s: &str) {
     }
 }
 
+pub fn put_bytes(bytes: &[u8]) {
+    let _lock = PRINT_LOCK.lock();
+    for &b in bytes {
+        sbi::console_putchar(b as usize);
+    }
+}
+
 struct Stdout;
 
 impl fmt::Write for Stdout {
@@ -22,6 +34,7 @@ impl fmt::Write for Stdout {
 }
 
 pub fn _print(args: fmt::Arguments) {
+    let _lock = PRINT_LOCK.lock();
     Stdout.write_fmt(args).unwrap();
 }
 
@@ -20,7 +20,9 @@ mod interrupt;
 mod lang_items;
 mod memory;
 mod process;
+mod rtc;
 mod sbi;
+mod smp;
 mod sync;
 mod syscall;
 mod timer;
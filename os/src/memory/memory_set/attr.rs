@@ -29,6 +29,13 @@ impl MemoryAttr {
         self
     }
 
+    pub fn is_user(&self) -> bool {
+        self.user
+    }
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
     pub fn apply(&self, entry: &mut PageEntry) {
         entry.set_present(true);
         entry.set_user(self.user);
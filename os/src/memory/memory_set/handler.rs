@@ -8,11 +8,12 @@ use crate::consts::PAGE_SIZE;
 use crate::memory::access_pa_via_va;
 use crate::memory::alloc_frame;
 use crate::memory::paging::PageTableImpl;
+use crate::smp::lock_flushing;
 
 use super::super::page_replace::PAGE_REPLACE_HANDLER;
 use super::attr::MemoryAttr;
 
-pub trait MemoryHandler: Debug + 'static {
+pub trait MemoryHandler: Debug + Send + 'static {
     fn box_clone(&self) -> Box<dyn MemoryHandler>;
     fn map(&self, pt: Arc<Mutex<PageTableImpl>>, va: usize, attr: &MemoryAttr);
     fn unmap(&self, pt: Arc<Mutex<PageTableImpl>>, va: usize);
@@ -165,7 +166,7 @@ impl MemoryHandler for ByFrameWithRpa {
         entry.clear_dirty();
         entry.clear_accessed();
         attr.apply(entry);
-        PAGE_REPLACE_HANDLER.lock().push_frame(va, pt.clone());
+        lock_flushing(&PAGE_REPLACE_HANDLER).push_frame(va, pt.clone());
     }
 
     fn unmap(&self, pt: Arc<Mutex<PageTableImpl>>, va: usize) {
@@ -218,8 +219,7 @@ impl MemoryHandler for ByFrameSwappingOut {
     }
 
     fn map(&self, pt: Arc<Mutex<PageTableImpl>>, va: usize, attr: &MemoryAttr) {
-        let frame = PAGE_REPLACE_HANDLER
-            .lock()
+        let frame = lock_flushing(&PAGE_REPLACE_HANDLER)
             .swap_out_one()
             .expect("failed to swap out page");
         let pa = frame.start_address().as_usize();
@@ -228,7 +228,7 @@ impl MemoryHandler for ByFrameSwappingOut {
         entry.clear_dirty();
         entry.clear_accessed();
         attr.apply(entry);
-        PAGE_REPLACE_HANDLER.lock().push_frame(va, pt.clone());
+        lock_flushing(&PAGE_REPLACE_HANDLER).push_frame(va, pt.clone());
     }
 
     fn unmap(&self, pt: Arc<Mutex<PageTableImpl>>, va: usize) {
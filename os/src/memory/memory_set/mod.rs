@@ -70,9 +70,26 @@ impl MemorySet {
             .find(|area| area.is_overlap_with(start, end))
             .is_none()
     }
+    // 从 addr 开始用户态可连续访问的区域的结束地址，write 为真时要求可写，不可访问时返回 addr
+    pub fn user_accessible_end(&self, addr: usize, write: bool) -> usize {
+        let mut end = addr;
+        while let Some(area) = self.areas.iter().find(|area| {
+            area.start <= end
+                && end < area.end
+                && area.attr.is_user()
+                && (!write || !area.attr.is_readonly())
+        }) {
+            end = area.end;
+        }
+        end
+    }
     pub unsafe fn activate(&self) {
         self.page_table.lock().activate();
     }
+    // 不打印日志的 activate，用于 exec 时替换当前地址空间
+    pub unsafe fn activate_quietly(&self) {
+        self.page_table.lock().activate_quietly();
+    }
     pub fn new() -> Self {
         let mut memory_set = MemorySet {
             areas: Vec::new(),
@@ -139,4 +156,17 @@ impl MemorySet {
     pub fn token(&self) -> usize {
         self.page_table.lock().token()
     }
+    // 将 data 写入地址 va 处，可在该地址空间未激活时使用，目标区域须已映射
+    pub fn write_bytes(&mut self, va: usize, data: &[u8]) {
+        let mut page_table = self.page_table.lock();
+        let mut copied = 0;
+        while copied < data.len() {
+            let addr = va + copied;
+            let offset = addr % PAGE_SIZE;
+            let len = (PAGE_SIZE - offset).min(data.len() - copied);
+            page_table.get_page_slice_mut(addr)[offset..offset + len]
+                .copy_from_slice(&data[copied..copied + len]);
+            copied += len;
+        }
+    }
 }
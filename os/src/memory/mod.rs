@@ -1,16 +1,21 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use buddy_system_allocator::LockedHeap;
 use riscv::addr::Frame;
+use riscv::asm::sfence_vma_all;
 use riscv::register::sstatus;
 
 use frame_allocator::SEGMENT_TREE_ALLOCATOR as FRAME_ALLOCATOR;
 use memory_set::{attr::MemoryAttr, handler::Linear, MemorySet};
 
 use crate::consts::*;
+use crate::rtc::RTC_BASE;
 
 mod frame_allocator;
 pub mod memory_set;
 pub mod page_replace;
 pub mod paging;
+pub mod user_access;
 
 pub fn init(l: usize, r: usize) {
     unsafe {
@@ -22,6 +27,18 @@ pub fn init(l: usize, r: usize) {
     println!("++++ setup memory!    ++++");
 }
 
+// 内核页表的 satp，其他 hart 启动时切换到它
+static KERNEL_SATP: AtomicUsize = AtomicUsize::new(0);
+
+/// Set up the memory of the other harts, they share the kernel page table with hart 0
+pub fn init_other() {
+    unsafe {
+        sstatus::set_sum();
+        asm!("csrw satp, $0" :: "r"(KERNEL_SATP.load(Ordering::Acquire)) :: "volatile");
+        sfence_vma_all();
+    }
+}
+
 pub fn alloc_frame() -> Option<Frame> {
     Some(Frame::of_ppn(FRAME_ALLOCATOR.lock().alloc()))
 }
@@ -64,6 +81,22 @@ pub fn kernel_remap() {
         Linear::new(PHYSICAL_MEMORY_OFFSET),
         None,
     );
+    // 各个 hart 的 PLIC 阈值与 claim/complete 寄存器
+    memory_set.push(
+        access_pa_via_va(0x0c20_0000),
+        access_pa_via_va(0x0c20_0000 + 0x2000 * MAX_HARTS),
+        MemoryAttr::new(),
+        Linear::new(PHYSICAL_MEMORY_OFFSET),
+        None,
+    );
+    // goldfish RTC
+    memory_set.push(
+        access_pa_via_va(RTC_BASE),
+        access_pa_via_va(RTC_BASE + PAGE_SIZE),
+        MemoryAttr::new(),
+        Linear::new(PHYSICAL_MEMORY_OFFSET),
+        None,
+    );
     memory_set.push(
         access_pa_via_va(0x1000_0000),
         access_pa_via_va(0x1000_1000),
@@ -75,6 +108,7 @@ pub fn kernel_remap() {
     unsafe {
         memory_set.activate();
     }
+    KERNEL_SATP.store(memory_set.token(), Ordering::Release);
 }
 
 #[global_allocator]
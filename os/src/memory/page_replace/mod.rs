@@ -40,6 +40,10 @@ pub trait PageReplace: Send {
                 }
                 entry.update();
                 println!("    vaddr {:x}", vaddr);
+                // 其他 hart 刷新 TLB 之后页帧才能被重新使用，等待时不能持有页表的锁
+                let token = table.token();
+                drop(table);
+                crate::smp::tlb_shootdown(token);
                 return Some(frame);
             }
         }
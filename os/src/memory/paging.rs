@@ -12,6 +12,7 @@ use crate::memory::{access_pa_via_va, alloc_frame, dealloc_frame};
 pub struct PageEntry(pub &'static mut PageTableEntry, Page);
 
 impl PageEntry {
+    /// Flush the entry from the TLB of the current hart, the other harts need `tlb_shootdown`.
     pub fn update(&mut self) {
         unsafe {
             sfence_vma(0, self.1.start_address().as_usize());
@@ -160,7 +161,13 @@ impl PageTableImpl {
         let old_token = Self::active_token();
         let new_token = self.token();
         println!("switch satp from {:#x} to {:#x}", old_token, new_token);
-        if new_token != old_token {
+        self.activate_quietly();
+    }
+
+    pub unsafe fn activate_quietly(&self) {
+        let new_token = self.token();
+        if new_token != Self::active_token() {
+            crate::smp::set_satp(new_token);
             Self::set_token(new_token);
             Self::flush_tlb();
         }
@@ -0,0 +1,75 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use crate::process::current_process;
+use crate::syscall::SysError;
+
+// 检查当前进程中 [addr, addr + len) 是否全部可被用户态访问，write 为真时还须可写
+// 内核运行时 sstatus.SUM 置位，未经检查的用户指针可能指向内核内存
+pub fn check_user(addr: usize, len: usize, write: bool) -> Result<(), SysError> {
+    let end = addr.checked_add(len).ok_or(SysError::EFAULT)?;
+    if user_accessible_end(addr, write)? >= end {
+        Ok(())
+    } else {
+        Err(SysError::EFAULT)
+    }
+}
+
+fn user_accessible_end(addr: usize, write: bool) -> Result<usize, SysError> {
+    // 先复制出地址空间再检查，不在持有进程锁时访问用户内存
+    let vm = current_process().lock().vm.clone().ok_or(SysError::EFAULT)?;
+    let end = vm.lock().user_accessible_end(addr, write);
+    Ok(end)
+}
+
+pub fn copy_from_user(dst: &mut [u8], src: *const u8) -> Result<(), SysError> {
+    check_user(src as usize, dst.len(), false)?;
+    unsafe {
+        dst.copy_from_slice(core::slice::from_raw_parts(src, dst.len()));
+    }
+    Ok(())
+}
+
+pub fn copy_to_user(dst: *mut u8, src: &[u8]) -> Result<(), SysError> {
+    check_user(dst as usize, src.len(), true)?;
+    unsafe {
+        core::slice::from_raw_parts_mut(dst, src.len()).copy_from_slice(src);
+    }
+    Ok(())
+}
+
+// 读取用户态的一个值，不要求对齐
+pub fn get_user<T: Copy>(src: *const T) -> Result<T, SysError> {
+    check_user(src as usize, size_of::<T>(), false)?;
+    Ok(unsafe { src.read_unaligned() })
+}
+
+// 向用户态写入一个值，不要求对齐
+pub fn put_user<T: Copy>(dst: *mut T, value: T) -> Result<(), SysError> {
+    check_user(dst as usize, size_of::<T>(), true)?;
+    unsafe { dst.write_unaligned(value) };
+    Ok(())
+}
+
+// 复制用户态以 '\0' 结尾的字符串，长度（不含 '\0'）不能超过 max
+pub fn strncpy_from_user(src: *const u8, max: usize) -> Result<String, SysError> {
+    let end = user_accessible_end(src as usize, false)?;
+    let mut bytes = Vec::new();
+    let mut addr = src as usize;
+    loop {
+        if addr >= end {
+            return Err(SysError::EFAULT);
+        }
+        let c = unsafe { *(addr as *const u8) };
+        if c == 0 {
+            break;
+        }
+        if bytes.len() == max {
+            return Err(SysError::ENAMETOOLONG);
+        }
+        bytes.push(c);
+        addr += 1;
+    }
+    String::from_utf8(bytes).map_err(|_| SysError::EINVAL)
+}
@@ -1,112 +1,492 @@
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 use spin::Mutex;
 
 use processor::Processor;
-use scheduler::RRScheduler;
-use structs::Thread;
-use thread_pool::ThreadPool;
+use realtime::Policy;
+use structs::{Process, Thread, ThreadStats};
+use thread_pool::{ThreadPool, WaitResult, WakeupHandle};
+use timer::TimerId;
 
+use crate::consts::MAX_HARTS;
+use crate::context::TrapFrame;
+use crate::fs::stdio::STDIN;
 use crate::fs::{INodeExt, ROOT_INODE};
-use crate::process::scheduler::StrideScheduler;
-use crate::process::timer::now;
+use crate::smp::hart_id;
+use crate::syscall::SysError;
+use crate::timer::{get_cycle, CLOCK_FREQ};
 
 pub mod processor;
+pub mod realtime;
 pub mod scheduler;
+pub mod session;
+pub mod signal;
 pub mod structs;
 pub mod thread_pool;
 pub mod timer;
 
 pub type Tid = usize;
-// pub type ExitCode = usize;
+pub type ExitCode = usize;
 
-pub static CPU: Processor = Processor::new();
+/// The init process owns the first thread added to the pool, it adopts all orphans.
+pub const INIT_TID: Tid = 0;
+
+// 每个 hart 一个 Processor
+const PROCESSOR: Processor = Processor::new();
+static CPUS: [Processor; MAX_HARTS] = [PROCESSOR; MAX_HARTS];
+
+/// The processor of the current hart
+pub fn cpu() -> &'static Processor {
+    &CPUS[hart_id()]
+}
+
+lazy_static! {
+    pub static ref THREAD_POOL: Mutex<ThreadPool> = Mutex::new(new_thread_pool());
+}
+
+// 每个 hart 一个调度器，调度算法相同
+fn new_thread_pool() -> ThreadPool {
+    let mut name = option_env!("SCHEDULER").unwrap_or("stride");
+    if scheduler::by_name(name).is_none() {
+        println!("unknown scheduler {}, use stride instead", name);
+        name = "stride";
+    }
+    let schedulers = (0..MAX_HARTS)
+        .map(|_| scheduler::by_name(name).unwrap())
+        .collect();
+    ThreadPool::new(100, schedulers)
+}
 
 pub fn init() {
-    let scheduler = StrideScheduler::new();
-    let thread_pool = ThreadPool::new(100, Box::new(scheduler));
-    let idle = Thread::new_kernel(Processor::idle_main as usize);
-    idle.append_initial_arguments([&CPU as *const Processor as usize, 0, 0]);
-    CPU.init(idle, Box::new(thread_pool));
+    init_other();
 
-    execute("rust/user_shell", None);
+    spawn_named("init", || {
+        let path = "rust/user_shell";
+        let pid = execute(path, alloc::vec![String::from(path)], Vec::new(), Some(current_pid()));
+        // shell 的会话拥有控制台，Ctrl-C 与 Ctrl-Z 发往它的前台进程组
+        if let Some(pid) = pid {
+            STDIN.attach(pid, pid);
+        }
+        // 回收所有退出的子线程，没有子线程时等待新的孤儿
+        loop {
+            if wait(0, false).is_none() {
+                park();
+            }
+        }
+    });
 
     println!("++++ setup process!   ++++");
 }
 
-pub fn execute(path: &str, host_tid: Option<Tid>) -> bool {
+/// Create the idle thread of the current hart, the other harts call it after hart 0 called `init`.
+pub fn init_other() {
+    let name = alloc::format!("idle{}", hart_id());
+    let idle = Thread::new_kernel(Processor::idle_main as usize, &name);
+    idle.append_initial_arguments([cpu() as *const Processor as usize, 0, 0]);
+    cpu().init(idle);
+}
+
+/// Load the program at `path` into a new user process, with arguments `args` and environment `envs`.
+///
+/// The `parent`, if given, must be the current process.
+pub fn execute(
+    path: &str,
+    args: Vec<String>,
+    envs: Vec<String>,
+    parent: Option<Tid>,
+) -> Option<Tid> {
     let find_result = ROOT_INODE.lookup(path);
     match find_result {
         Ok(inode) => {
             let data = inode.read_as_vec().unwrap();
-            let user_thread = unsafe { Thread::new_user(data.as_slice(), host_tid) };
-            CPU.add_thread(user_thread);
-            true
+            let user_thread =
+                match unsafe { Thread::new_user(data.as_slice(), &args, &envs, parent) } {
+                    Ok(thread) => thread,
+                    Err(_) => {
+                        println!("{} is not an executable!", path);
+                        return None;
+                    }
+                };
+            let tid = cpu().add_thread(user_thread);
+            if parent.is_some() {
+                current_process().lock().children.push(tid);
+            }
+            Some(tid)
         }
         Err(_) => {
             println!("command not found!");
-            false
+            None
         }
     }
 }
 
+/// Replace the program of the current process with the one at `path`.
+///
+/// Only a process with a single thread may exec, on success `tf` returns to the entry of the new program.
+pub fn exec(
+    path: &str,
+    args: Vec<String>,
+    envs: Vec<String>,
+    tf: &mut TrapFrame,
+) -> Result<(), SysError> {
+    if current_process().lock().threads.len() > 1 {
+        return Err(SysError::EINVAL);
+    }
+    let data = ROOT_INODE.lookup(path)?.read_as_vec()?;
+    unsafe { current_thread_mut().exec(data.as_slice(), &args, &envs, tf) }
+}
+
 pub fn run() {
-    CPU.run();
+    cpu().run();
+}
+
+pub fn exit(code: ExitCode) {
+    cpu().exit(code);
+}
+
+/// Kill the current process because of signal `sig`, its exit code is `128 + sig` as in shells.
+///
+/// The other threads of the process exit on their next return to user mode.
+pub fn kill_current(sig: usize) {
+    let code = 128 + sig;
+    let pid = {
+        let mut proc = current_process().lock();
+        proc.killed = Some(code);
+        proc.pid
+    };
+    THREAD_POOL.lock().wakeup_process(pid);
+    exit(code);
+}
+
+/// Exit the current thread if its process has been killed, called before returning to user mode.
+pub fn exit_if_killed() {
+    let killed = current_process().lock().killed;
+    if let Some(code) = killed {
+        exit(code);
+    }
+}
+
+/// The status returned by `wait` for a stopped child, the same as Linux.
+pub fn stopped_status(sig: usize) -> ExitCode {
+    0x7f | sig << 8
+}
+
+/// Reap the child process `pid` (or any child if `pid` is 0) if it has exited, without blocking.
+///
+/// If `untraced`, also return a child which has stopped, with the status given by `stopped_status`.
+/// Returns `Ok(None)` if the current process has no such child, `Err(EAGAIN)` if it is running.
+pub fn try_wait(pid: Tid, untraced: bool) -> Result<Option<(Tid, ExitCode)>, SysError> {
+    let result = {
+        let mut pool = THREAD_POOL.lock();
+        pool.try_wait(&mut current_process().lock(), pid, untraced)
+    };
+    match result {
+        WaitResult::Exited(pid, code) => Ok(Some((pid, code))),
+        WaitResult::Stopped(pid, sig) => Ok(Some((pid, stopped_status(sig)))),
+        WaitResult::NotFound => Ok(None),
+        WaitResult::Running => Err(SysError::EAGAIN),
+    }
 }
 
-pub fn exit(code: usize) {
-    CPU.exit(code);
+/// Block until the child process `pid` (or any child if `pid` is 0) exits, then reap it.
+///
+/// If `untraced`, also return when a child stops, with the status given by `stopped_status`.
+/// Returns `None` if the current process has no such child.
+pub fn wait(pid: Tid, untraced: bool) -> Option<(Tid, ExitCode)> {
+    loop {
+        // park 之后可能换到了别的 hart 上，每次都重新取当前进程
+        match try_wait(pid, untraced) {
+            Ok(result) => return result,
+            // 进程已被杀死时不再等待
+            Err(_) if current_process().lock().killed.is_some() => return None,
+            // woken up again once a child exits
+            Err(_) => park(),
+        }
+    }
 }
 
 pub fn yield_now() {
-    CPU.yield_now();
+    cpu().yield_now();
 }
 
-pub fn wake_up(tid: Tid) {
-    CPU.wake_up(tid);
+/// Yield on behalf of the user, a SCHED_DEADLINE thread finishes its job of this period.
+pub fn sched_yield() {
+    THREAD_POOL.lock().on_yield(current_tid());
+    yield_now();
+}
+
+/// Reschedule at once if a real-time thread is waiting for the current hart.
+pub fn preempt() {
+    cpu().preempt();
+}
+
+pub fn wake_up(handle: WakeupHandle) {
+    cpu().wake_up(handle);
 }
 pub fn current_tid() -> usize {
-    CPU.current_tid()
+    cpu().current_tid()
+}
+
+/// The handle to wake up the current thread, a stale one does nothing after it exits.
+pub fn current_handle() -> WakeupHandle {
+    THREAD_POOL.lock().handle(current_tid())
 }
 
 pub fn current_thread_mut() -> &'static mut Thread {
-    CPU.current_thread_mut()
+    cpu().current_thread_mut()
+}
+
+pub fn current_process() -> &'static Mutex<Process> {
+    &current_thread_mut().proc
+}
+
+pub fn current_pid() -> Tid {
+    current_process().lock().pid
 }
 
 pub fn add_thread(thread: Box<Thread>) -> usize {
-    CPU.add_thread(thread)
+    cpu().add_thread(thread)
 }
 
 pub fn park() {
-    CPU.park();
+    cpu().park();
+}
+
+/// The statistics of the current thread, up to now.
+pub fn current_stats() -> ThreadStats {
+    let thread = current_thread_mut();
+    thread.usage.charge(false);
+    let mut stats = THREAD_POOL.lock().stats(thread.tid).unwrap();
+    stats.usage = thread.usage;
+    stats
+}
+
+/// The statistics of the current process and of its reaped children.
+///
+/// The other running threads are counted up to the last time they were switched out.
+pub fn process_stats() -> (ThreadStats, ThreadStats) {
+    let current = current_stats();
+    let tid = current_tid();
+    let pool = THREAD_POOL.lock();
+    let proc = current_process().lock();
+    let mut stats = proc.stats;
+    for &t in proc.threads.iter() {
+        if t == tid {
+            stats.add(&current);
+        } else if let Some(other) = pool.stats(t) {
+            stats.add(&other);
+        }
+    }
+    (stats, proc.children_stats)
+}
+
+/// Set the priority of the current thread, the larger the more CPU time it gets.
+pub fn set_priority(priority: usize) {
+    THREAD_POOL.lock().set_priority(current_tid(), priority);
+}
+
+/// The nice value of thread `tid`, `None` if it does not exist.
+pub fn get_nice(tid: Tid) -> Option<isize> {
+    THREAD_POOL.lock().get_nice(tid)
+}
+
+/// Set the nice value of thread `tid`, returns false if it does not exist.
+pub fn set_nice(tid: Tid, nice: isize) -> bool {
+    THREAD_POOL.lock().set_nice(tid, nice)
+}
+
+/// Change the scheduling policy of the current thread, `None` for the normal class.
+pub fn set_policy(policy: Option<Policy>) -> Result<(), SysError> {
+    THREAD_POOL.lock().set_policy(current_tid(), policy)
 }
 
 lazy_static! {
     static ref TIMER: Mutex<timer::Timer> = Mutex::new(timer::Timer::default());
 }
 
+/// Called on each timer interrupt, the next one is programmed before the current thread is switched out.
+///
+/// A busy hart is interrupted at the end of each time slice, an idle hart only when a timer event is due.
 pub fn tick() {
-    CPU.tick();
-    TIMER.lock().tick(now());
+    let next = {
+        let mut timer = TIMER.lock();
+        timer.tick(get_cycle());
+        timer.next()
+    };
+    if cpu().is_idle() {
+        crate::timer::set_next_event(next);
+    } else {
+        let slice = crate::timer::next_slice();
+        crate::timer::set_next_event(Some(next.map_or(slice, |next| next.min(slice))));
+    }
+    cpu().tick();
+}
+
+/// Call `callback` at `deadline` in timer cycles, from the timer interrupt of some hart.
+pub fn add_timer(deadline: u64, callback: impl FnMut() + Send + Sync + 'static) -> TimerId {
+    let id = TIMER.lock().add(deadline, callback);
+    // 其他 hart 可能都在空闲，由当前 hart 负责按时触发
+    crate::timer::set_next_event_before(deadline);
+    id
+}
+
+/// Cancel the timer `id`, its callback is not called any more.
+///
+/// Returns when it would have expired next and its period, `None` if it has expired.
+pub fn cancel_timer(id: TimerId) -> Option<(u64, Option<u64>)> {
+    TIMER.lock().cancel(id)
+}
+
+/// When the idle current hart should wake up: the next timer event,
+/// or the next period of its real-time threads. `None` if nothing is due.
+pub fn idle_deadline() -> Option<u64> {
+    let timer = TIMER.lock().next();
+    let release = THREAD_POOL.lock().next_release(hart_id());
+    match (timer, release) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Sleep until `deadline` in timer cycles, returns `Err(EINTR)` if a signal arrives first.
+pub fn sleep_until(deadline: u64) -> Result<(), SysError> {
+    let handle = current_handle();
+    let timer = add_timer(deadline, move || wake_up(handle));
+    // 其他唤醒不算数，睡够了或收到信号才返回
+    while get_cycle() < deadline {
+        if signal::interrupted() {
+            // 提前醒来时取消定时器，以免它以后多唤醒一次
+            cancel_timer(timer);
+            return Err(SysError::EINTR);
+        }
+        park();
+    }
+    Ok(())
+}
+
+// 定时器的剩余时间与周期，已经到期或取消的为 (0, 0)
+fn time_left(timer: Option<(u64, Option<u64>)>) -> (u64, u64) {
+    let now = get_cycle();
+    timer.map_or((0, 0), |(deadline, period)| {
+        (deadline.saturating_sub(now).max(1), period.unwrap_or(0))
+    })
+}
+
+/// The real-time interval timer of the current process, as (time left, interval) in timer cycles.
+///
+/// Both are 0 if it is disarmed.
+pub fn get_real_timer() -> (u64, u64) {
+    let timer = TIMER.lock();
+    let id = current_process().lock().real_timer;
+    time_left(id.and_then(|id| timer.get(id)))
+}
+
+/// Arm the real-time interval timer of the current process, as setitimer(ITIMER_REAL).
+///
+/// SIGALRM is sent after `value` cycles, and then every `interval` cycles if it is not 0.
+/// A zero `value` disarms the timer. Returns the old setting as `get_real_timer`.
+pub fn set_real_timer(value: u64, interval: u64) -> (u64, u64) {
+    let pid = current_pid();
+    let deadline = get_cycle().saturating_add(value);
+    let alarm = move || {
+        signal::send(pid, signal::SIGALRM).ok();
+    };
+    // 先加锁 TIMER 再加锁进程，与定时器回调中发送信号的顺序一致
+    let mut timer = TIMER.lock();
+    let mut proc = current_process().lock();
+    let old = time_left(proc.real_timer.take().and_then(|id| timer.cancel(id)));
+    if value != 0 {
+        proc.real_timer = Some(match interval {
+            0 => timer.add(deadline, alarm),
+            interval => timer.add_periodic(deadline, interval, alarm),
+        });
+        crate::timer::set_next_event_before(deadline);
+    }
+    old
 }
 
 pub fn sleep(sec: usize) {
-    let tid = current_tid();
-    TIMER
-        .lock()
-        .add(now() + (sec * 100) as u64, move || wake_up(tid));
-    park();
+    sleep_until(get_cycle() + sec as u64 * CLOCK_FREQ).ok();
+}
+
+/// A kernel thread created by `spawn`, dropping the handle detaches the thread.
+pub struct JoinHandle<T> {
+    tid: Tid,
+    packet: Arc<Mutex<Packet<T>>>,
+}
+
+// 线程函数的返回值，以及正在等待它的线程
+struct Packet<T> {
+    result: Option<T>,
+    waiter: Option<WakeupHandle>,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn tid(&self) -> Tid {
+        self.tid
+    }
+
+    /// Wait for the thread to return from its function, and take the returned value.
+    pub fn join(self) -> T {
+        loop {
+            {
+                let mut packet = self.packet.lock();
+                if let Some(result) = packet.result.take() {
+                    return result;
+                }
+                packet.waiter = Some(current_handle());
+            }
+            // 返回值在 park 之前放好时 park 直接返回
+            park();
+        }
+    }
 }
 
 /// Spawn a new kernel thread from function `f`.
-pub fn spawn<F>(f: F)
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    spawn_named("", f)
+}
+
+/// Spawn a new kernel thread named `name` from function `f`.
+pub fn spawn_named<F, T>(name: &str, f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let packet = Arc::new(Mutex::new(Packet {
+        result: None,
+        waiter: None,
+    }));
+    let their_packet = packet.clone();
+    let tid = start_kernel_thread(name, move || {
+        let result = f();
+        let waiter = {
+            let mut packet = their_packet.lock();
+            packet.result = Some(result);
+            packet.waiter.take()
+        };
+        if let Some(waiter) = waiter {
+            wake_up(waiter);
+        }
+    });
+    JoinHandle { tid, packet }
+}
+
+fn start_kernel_thread<F>(name: &str, f: F) -> Tid
 where
     F: FnOnce() + Send + 'static,
 {
     let f = Box::into_raw(Box::new(f));
-    let new_thread = Thread::new_kernel(entry::<F> as usize);
+    let new_thread = Thread::new_kernel(entry::<F> as usize, name);
     new_thread.append_initial_arguments([f as usize, 0, 0]);
-    CPU.add_thread(new_thread);
+    let tid = cpu().add_thread(new_thread);
 
     // define a normal function, pass the function object from argument
     extern "C" fn entry<F>(f: usize) -> !
@@ -118,4 +498,6 @@ where
         exit(0);
         unreachable!()
     }
+
+    tid
 }
@@ -1,13 +1,19 @@
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::cell::UnsafeCell;
 
+use riscv::register::satp;
+
+use crate::fs::stdio::STDIN;
 use crate::interrupt::*;
 use crate::process::structs::*;
-use crate::process::thread_pool::ThreadPool;
-use crate::process::Tid;
+use crate::process::thread_pool::WakeupHandle;
+use crate::process::{cancel_timer, idle_deadline, ExitCode, Tid, INIT_TID, THREAD_POOL};
+use crate::smp::{hart_id, set_satp};
+use crate::timer::{next_slice, set_next_event, set_next_event_before};
 
+/// The state of one hart, only accessed by the hart itself
 pub struct ProcessorInner {
-    pub pool: Box<ThreadPool>,
     idle: Box<Thread>,
     current: Option<(Tid, Box<Thread>)>,
 }
@@ -25,10 +31,9 @@ impl Processor {
         }
     }
 
-    pub fn init(&self, idle: Box<Thread>, pool: Box<ThreadPool>) {
+    pub fn init(&self, idle: Box<Thread>) {
         unsafe {
             *self.inner.get() = Some(ProcessorInner {
-                pool,
                 idle,
                 current: None,
             });
@@ -42,23 +47,32 @@ impl Processor {
     }
 
     pub fn add_thread(&self, thread: Box<Thread>) -> Tid {
-        self.inner().pool.add(thread)
+        THREAD_POOL.lock().add(thread)
     }
 
     pub fn idle_main(&self) -> ! {
         let inner = self.inner();
         disable_and_store();
         loop {
-            if let Some(thread) = inner.pool.acquire() {
+            let thread = THREAD_POOL.lock().acquire(hart_id());
+            if let Some(mut thread) = thread {
+                // 运行线程时按时间片产生时钟中断
+                set_next_event_before(next_slice());
+                thread.1.usage.start();
+                // 切换页表之前登记，此后修改这个页表的 hart 会等待当前 hart 刷新 TLB
+                set_satp(thread.1.context.satp());
                 inner.current = Some(thread);
                 inner
                     .idle
                     .switch_to(&mut *inner.current.as_mut().unwrap().1);
+                set_satp(satp::read().bits());
                 let (tid, thread) = inner.current.take().unwrap();
-                inner.pool.retrieve(tid, thread);
+                THREAD_POOL.lock().retrieve(tid, thread);
                 enable();
                 disable_and_store();
             } else {
+                // 空闲时不需要时间片，只在有事要做时醒来
+                set_next_event(idle_deadline());
                 enable_and_wfi();
                 disable_and_store();
             }
@@ -68,26 +82,91 @@ impl Processor {
     pub fn tick(&self) {
         let inner = self.inner();
         if !inner.current.is_none() {
-            if inner.pool.tick() {
+            let resched = THREAD_POOL.lock().tick(hart_id());
+            if resched {
                 let flags = disable_and_store();
-
-                inner.current.as_mut().unwrap().1.switch_to(&mut inner.idle);
-
+                self.switch_out(false);
                 restore(flags);
             }
         }
     }
 
-    pub fn exit(&self, code: usize) -> ! {
+    // 切换回 idle 线程，voluntary 表示当前线程主动让出 CPU
+    fn switch_out(&self, voluntary: bool) {
+        let inner = self.inner();
+        let current_thread = &mut inner.current.as_mut().unwrap().1;
+        current_thread.usage.charge(false);
+        if voluntary {
+            current_thread.usage.nvcsw += 1;
+        } else {
+            current_thread.usage.nivcsw += 1;
+        }
+        current_thread.switch_to(&mut inner.idle);
+    }
+
+    pub fn exit(&self, code: ExitCode) -> ! {
         disable_and_store();
         let inner = self.inner();
         let tid = inner.current.as_ref().unwrap().0;
 
-        inner.pool.exit(tid);
-        println!("thread {} exited, exit code = {}", tid, code);
+        let stats = {
+            let mut pool = THREAD_POOL.lock();
+            pool.exit(tid, code);
+            let mut stats = pool.stats(tid).unwrap();
+            let usage = &mut inner.current.as_mut().unwrap().1.usage;
+            usage.charge(false);
+            stats.usage = *usage;
+            stats
+        };
+        let name = &inner.current.as_ref().unwrap().1.name;
+        if name.is_empty() {
+            println!("thread {} exited, exit code = {}", tid, code);
+        } else {
+            println!("thread {} ({}) exited, exit code = {}", tid, name, code);
+        }
+        if stats.deadline_misses > 0 {
+            println!("thread {} missed {} deadlines", tid, stats.deadline_misses);
+        }
 
-        if let Some(wait) = inner.current.as_ref().unwrap().1.wait {
-            inner.pool.wakeup(wait);
+        {
+            let proc = inner.current.as_ref().unwrap().1.proc.clone();
+            // 持有进程的锁时不能操作线程池，释放的资源（如管道）也可能要唤醒其他线程
+            let (last, resources, children, real_timer) = {
+                let mut proc = proc.lock();
+                proc.threads.retain(|&t| t != tid);
+                proc.stats.add(&stats);
+                // 最后一个线程退出时整个进程随之退出
+                if proc.threads.is_empty() {
+                    let children: Vec<Tid> = proc.children.drain(..).collect();
+                    let real_timer = proc.real_timer.take();
+                    (true, Some(proc.exit()), children, real_timer)
+                } else {
+                    (false, None, Vec::new(), None)
+                }
+            };
+            drop(resources);
+            // 进程退出后 pid 可能被重新使用，不能再向它发送 SIGALRM
+            if let Some(id) = real_timer {
+                cancel_timer(id);
+            }
+            if last {
+                let (pid, sid) = {
+                    let proc = proc.lock();
+                    (proc.pid, proc.sid)
+                };
+                {
+                    let mut pool = THREAD_POOL.lock();
+                    // 孤儿进程交给 init 进程回收
+                    for child in children {
+                        pool.reparent(child, INIT_TID);
+                    }
+                    pool.exit_process(pid, code);
+                }
+                // 会话首进程退出时控制台随之挂断
+                if pid == sid {
+                    STDIN.hangup(sid);
+                }
+            }
         }
 
         inner.current.as_mut().unwrap().1.switch_to(&mut inner.idle);
@@ -102,18 +181,32 @@ impl Processor {
     pub fn yield_now(&self) {
         let inner = self.inner();
         if !inner.current.is_none() {
-            unsafe {
+            let flags = disable_and_store();
+            self.switch_out(true);
+            restore(flags);
+        }
+    }
+
+    pub fn wake_up(&self, handle: WakeupHandle) {
+        THREAD_POOL.lock().wakeup_handle(handle);
+    }
+
+    /// Switch out the current thread if a real-time thread is waiting, called on a reschedule IPI.
+    pub fn preempt(&self) {
+        let inner = self.inner();
+        if !inner.current.is_none() {
+            let resched = THREAD_POOL.lock().need_resched(hart_id());
+            if resched {
                 let flags = disable_and_store();
-                let current_thread = &mut inner.current.as_mut().unwrap().1;
-                current_thread.switch_to(&mut *inner.idle);
+                self.switch_out(false);
                 restore(flags);
             }
         }
     }
 
-    pub fn wake_up(&self, tid: Tid) {
-        let inner = self.inner();
-        inner.pool.wakeup(tid);
+    /// Whether the current hart is running its idle thread
+    pub fn is_idle(&self) -> bool {
+        self.inner().current.is_none()
     }
 
     pub fn current_tid(&self) -> usize {
@@ -124,8 +217,11 @@ impl Processor {
         self.inner().current.as_mut().unwrap().1.as_mut()
     }
 
+    /// Sleep until woken up, returns at once if woken up since the last `park`.
     pub fn park(&self) {
-        self.inner().pool.set_sleep(self.current_tid());
-        self.yield_now();
+        let sleep = THREAD_POOL.lock().set_sleep(self.current_tid());
+        if sleep {
+            self.yield_now();
+        }
     }
 }
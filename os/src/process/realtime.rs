@@ -0,0 +1,385 @@
+//! The real-time scheduling class, always served before the normal scheduler.
+//!
+//! SCHED_DEADLINE threads are scheduled by EDF and served first, then SCHED_FIFO and SCHED_RR
+//! threads by their fixed priority. Time is measured in timer cycles.
+
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec::Vec;
+
+use super::Tid;
+use crate::syscall::SysError;
+use crate::timer::get_cycle;
+
+// sched_setscheduler 的调度策略，与 Linux 一致
+pub const SCHED_OTHER: usize = 0;
+pub const SCHED_FIFO: usize = 1;
+pub const SCHED_RR: usize = 2;
+pub const SCHED_DEADLINE: usize = 6;
+
+pub const RT_PRIO_MIN: usize = 1;
+pub const RT_PRIO_MAX: usize = 99;
+
+// SCHED_RR 线程的时间片，单位为时钟中断
+const RT_RR_SLICE: usize = 10;
+// 实时线程的 CPU 带宽上限，单位为百万分之一，留给普通线程一点时间
+const RT_BANDWIDTH: u64 = 950_000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Policy {
+    Fifo(usize),
+    RoundRobin(usize),
+    /// Each `period` the thread may run for `runtime`, and should finish before `deadline`
+    Deadline {
+        runtime: u64,
+        deadline: u64,
+        period: u64,
+    },
+}
+
+impl Policy {
+    /// Check the parameters, `Err(EINVAL)` if they make no sense.
+    pub fn validate(&self) -> Result<(), SysError> {
+        let ok = match *self {
+            Policy::Fifo(prio) | Policy::RoundRobin(prio) => {
+                prio >= RT_PRIO_MIN && prio <= RT_PRIO_MAX
+            }
+            Policy::Deadline {
+                runtime,
+                deadline,
+                period,
+            } => runtime > 0 && runtime <= deadline && deadline <= period,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(SysError::EINVAL)
+        }
+    }
+
+    // 占用的 CPU 带宽，deadline 小于 period 时按 runtime / deadline 保守估计
+    fn bandwidth(&self) -> u64 {
+        match *self {
+            Policy::Deadline {
+                runtime, deadline, ..
+            } => runtime * 1_000_000 / deadline,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum State {
+    Running,
+    Ready,
+    /// 本周期的 runtime 已用完或已完成，等到下一周期再运行
+    Throttled,
+    Sleeping,
+}
+
+struct RtInfo {
+    policy: Policy,
+    state: State,
+    // SCHED_RR 剩余的时间片
+    time: usize,
+    // 被更高优先级的线程抢占，重新就绪时排在同优先级线程的最前面
+    preempted: bool,
+    // 以下用于 SCHED_DEADLINE：本周期的开始、绝对截止时间、剩余的 runtime
+    release: u64,
+    deadline: u64,
+    budget: i64,
+    done: bool,
+    misses: usize,
+}
+
+impl RtInfo {
+    // 从 release 开始新的一个周期
+    fn new_job(&mut self, release: u64) {
+        if let Policy::Deadline {
+            runtime, deadline, ..
+        } = self.policy
+        {
+            self.release = release;
+            self.deadline = release + deadline;
+            self.budget = runtime as i64;
+            self.done = false;
+        }
+    }
+
+    fn period(&self) -> u64 {
+        match self.policy {
+            Policy::Deadline { period, .. } => period,
+            _ => 0,
+        }
+    }
+}
+
+pub struct RealtimeScheduler {
+    threads: BTreeMap<Tid, RtInfo>,
+    // 按截止时间排序的就绪 SCHED_DEADLINE 线程
+    edf: BTreeSet<(u64, Tid)>,
+    // 每个优先级一个队列，下标为优先级
+    fixed: Vec<VecDeque<Tid>>,
+    current: Option<Tid>,
+    // current 开始运行的时间
+    start: u64,
+    bandwidth: u64,
+}
+
+impl RealtimeScheduler {
+    pub fn new() -> Self {
+        let mut fixed = Vec::new();
+        fixed.resize_with(RT_PRIO_MAX + 1, Default::default);
+        RealtimeScheduler {
+            threads: BTreeMap::new(),
+            edf: BTreeSet::new(),
+            fixed,
+            current: None,
+            start: 0,
+            bandwidth: 0,
+        }
+    }
+
+    pub fn contains(&self, tid: Tid) -> bool {
+        self.threads.contains_key(&tid)
+    }
+
+    /// The real-time thread running now, if any
+    pub fn current(&self) -> Option<Tid> {
+        self.current
+    }
+
+    /// How many deadlines thread `tid` has missed
+    pub fn deadline_misses(&self, tid: Tid) -> usize {
+        self.threads.get(&tid).map_or(0, |info| info.misses)
+    }
+
+    /// Move the running thread `tid` into the real-time class, or change its policy.
+    ///
+    /// A SCHED_DEADLINE thread is only admitted if the total bandwidth stays below `RT_BANDWIDTH`,
+    /// otherwise `Err(EBUSY)` is returned and nothing changes.
+    pub fn set_policy(&mut self, tid: Tid, policy: Policy) -> Result<(), SysError> {
+        let old = self
+            .threads
+            .get(&tid)
+            .map_or(0, |info| info.policy.bandwidth());
+        policy.validate()?;
+        if self.bandwidth - old + policy.bandwidth() > RT_BANDWIDTH {
+            return Err(SysError::EBUSY);
+        }
+        self.bandwidth = self.bandwidth - old + policy.bandwidth();
+        let now = get_cycle();
+        let misses = self.deadline_misses(tid);
+        let mut info = RtInfo {
+            policy,
+            state: State::Running,
+            time: RT_RR_SLICE,
+            preempted: false,
+            release: 0,
+            deadline: 0,
+            budget: 0,
+            done: false,
+            misses,
+        };
+        info.new_job(now);
+        self.threads.insert(tid, info);
+        self.current = Some(tid);
+        self.start = now;
+        Ok(())
+    }
+
+    // 扣除 current 已运行的时间
+    fn account(&mut self, now: u64) {
+        if let Some(tid) = self.current {
+            let info = self.threads.get_mut(&tid).unwrap();
+            info.budget -= (now - self.start) as i64;
+        }
+        self.start = now;
+    }
+
+    // 到了下一周期的线程重新就绪，错过截止时间的线程记录一次并开始下一周期
+    fn update(&mut self, now: u64) {
+        for (&tid, info) in self.threads.iter_mut() {
+            if let Policy::Deadline { .. } = info.policy {
+                if info.state == State::Throttled && info.release <= now {
+                    info.state = State::Ready;
+                    self.edf.insert((info.deadline, tid));
+                } else if !info.done && info.state != State::Sleeping && now > info.deadline {
+                    info.misses += 1;
+                    let ready = self.edf.remove(&(info.deadline, tid));
+                    info.new_job(info.release + info.period());
+                    if info.deadline <= now {
+                        info.new_job(now);
+                    }
+                    if ready {
+                        self.edf.insert((info.deadline, tid));
+                    }
+                }
+            }
+        }
+    }
+
+    /// When the next period of a throttled thread begins, the hart should not sleep past it.
+    pub fn next_release(&self) -> Option<u64> {
+        self.threads
+            .values()
+            .filter(|info| info.state == State::Throttled)
+            .map(|info| info.release)
+            .min()
+    }
+
+    /// The number of queued real-time threads, throttled ones are not counted
+    pub fn ready_count(&self) -> usize {
+        self.edf.len() + self.fixed.iter().map(|q| q.len()).sum::<usize>()
+    }
+
+    /// Whether any real-time thread is ready
+    pub fn has_ready(&self) -> bool {
+        !self.edf.is_empty() || self.fixed.iter().any(|q| !q.is_empty())
+    }
+
+    // 优先级高于 prio 的线程是否已就绪
+    fn has_higher(&self, prio: usize) -> bool {
+        !self.edf.is_empty() || self.fixed[prio + 1..].iter().any(|q| !q.is_empty())
+    }
+
+    /// A running or woken thread `tid` becomes ready.
+    pub fn push(&mut self, tid: Tid) {
+        let now = get_cycle();
+        if self.current == Some(tid) {
+            self.account(now);
+            self.current = None;
+        }
+        let info = self.threads.get_mut(&tid).unwrap();
+        let preempted = core::mem::replace(&mut info.preempted, false);
+        match info.policy {
+            Policy::Fifo(prio) | Policy::RoundRobin(prio) if preempted => {
+                self.fixed[prio].push_front(tid)
+            }
+            Policy::Fifo(prio) => self.fixed[prio].push_back(tid),
+            Policy::RoundRobin(prio) => {
+                if info.time == 0 {
+                    info.time = RT_RR_SLICE;
+                }
+                self.fixed[prio].push_back(tid);
+            }
+            Policy::Deadline { .. } => {
+                if info.done || info.budget <= 0 {
+                    // runtime 用完却没有完成，同样算作错过截止时间
+                    if !info.done {
+                        info.misses += 1;
+                    }
+                    info.new_job(info.release + info.period());
+                    if info.deadline <= now {
+                        info.new_job(now);
+                    }
+                }
+                if info.release > now {
+                    info.state = State::Throttled;
+                    return;
+                }
+                self.edf.insert((info.deadline, tid));
+            }
+        }
+        info.state = State::Ready;
+    }
+
+    pub fn pop(&mut self) -> Option<Tid> {
+        let now = get_cycle();
+        self.update(now);
+        let tid = match self.edf.iter().next() {
+            Some(&key) => {
+                self.edf.remove(&key);
+                Some(key.1)
+            }
+            None => self.fixed.iter_mut().rev().find_map(|q| q.pop_front()),
+        };
+        if let Some(tid) = tid {
+            self.threads.get_mut(&tid).unwrap().state = State::Running;
+        }
+        self.current = tid;
+        self.start = now;
+        tid
+    }
+
+    /// Called on each tick, returns whether the current thread should be preempted.
+    ///
+    /// If a normal thread is running, it is preempted once any real-time thread is ready.
+    pub fn tick(&mut self) -> bool {
+        let now = get_cycle();
+        self.account(now);
+        self.update(now);
+        let tid = match self.current {
+            Some(tid) => tid,
+            None => return self.has_ready(),
+        };
+        let higher = match self.threads[&tid].policy {
+            Policy::Fifo(prio) | Policy::RoundRobin(prio) => self.has_higher(prio),
+            Policy::Deadline { .. } => false,
+        };
+        let info = self.threads.get_mut(&tid).unwrap();
+        match info.policy {
+            Policy::Fifo(_) => {
+                info.preempted = higher;
+                higher
+            }
+            Policy::RoundRobin(_) => {
+                info.time = info.time.saturating_sub(1);
+                // 用完时间片的线程排到队尾
+                info.preempted = info.time > 0 && higher;
+                info.time == 0 || higher
+            }
+            Policy::Deadline { .. } => {
+                let deadline = info.deadline;
+                info.budget <= 0 || self.edf.iter().next().map_or(false, |&(d, _)| d < deadline)
+            }
+        }
+    }
+
+    /// The running SCHED_DEADLINE thread `tid` has finished its job of this period.
+    pub fn job_done(&mut self, tid: Tid) {
+        if let Some(info) = self.threads.get_mut(&tid) {
+            info.done = true;
+        }
+    }
+
+    pub fn on_block(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.account(get_cycle());
+            self.current = None;
+        }
+        if let Some(info) = self.threads.get_mut(&tid) {
+            info.state = State::Sleeping;
+            info.preempted = false;
+        }
+    }
+
+    pub fn on_wakeup(&mut self, tid: Tid) {
+        let now = get_cycle();
+        let info = self.threads.get_mut(&tid).unwrap();
+        // 睡过了截止时间，说明在等待事件，从现在开始新的周期而不算作错过
+        if let Policy::Deadline { .. } = info.policy {
+            if now >= info.deadline {
+                info.new_job(now);
+            }
+        }
+        self.push(tid);
+    }
+
+    /// Remove thread `tid` from the real-time class, returns how many deadlines it has missed.
+    pub fn exit(&mut self, tid: Tid) -> usize {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        match self.threads.remove(&tid) {
+            Some(info) => {
+                self.bandwidth -= info.policy.bandwidth();
+                self.edf.remove(&(info.deadline, tid));
+                if let Policy::Fifo(prio) | Policy::RoundRobin(prio) = info.policy {
+                    self.fixed[prio].retain(|&t| t != tid);
+                }
+                info.misses
+            }
+            None => 0,
+        }
+    }
+}
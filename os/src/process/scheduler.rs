@@ -1,13 +1,38 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, BinaryHeap, VecDeque};
 use alloc::vec::Vec;
-use core::cmp::min;
+use core::cmp::{min, Ordering};
 
 use super::Tid;
+use crate::timer::{get_cycle, TIMEBASE};
 
-pub trait Scheduler {
+pub trait Scheduler: Send {
     fn push(&mut self, tid: Tid);
     fn pop(&mut self) -> Option<Tid>;
     fn tick(&mut self) -> bool;
     fn exit(&mut self, tid: Tid);
+
+    /// 设置线程的优先级，数值越大优先级越高，不支持优先级的策略忽略它
+    fn set_priority(&mut self, _tid: Tid, _priority: usize) {}
+    /// 设置线程的 nice 值，范围为 -20 ~ 19，数值越小优先级越高
+    fn set_nice(&mut self, _tid: Tid, _nice: isize) {}
+    /// 睡眠的线程被唤醒，重新加入就绪队列
+    fn on_wakeup(&mut self, tid: Tid) {
+        self.push(tid);
+    }
+    /// 正在运行的线程进入睡眠
+    fn on_block(&mut self, _tid: Tid) {}
+}
+
+/// 按名字创建调度器，名字在编译时由环境变量 SCHEDULER 给出
+pub fn by_name(name: &str) -> Option<Box<dyn Scheduler>> {
+    match name {
+        "rr" => Some(Box::new(RRScheduler::new(5))),
+        "stride" => Some(Box::new(StrideScheduler::new())),
+        "mlfq" => Some(Box::new(MLFQScheduler::new())),
+        "cfs" => Some(Box::new(CFSScheduler::new())),
+        _ => None,
+    }
 }
 
 #[derive(Default)]
@@ -98,14 +123,62 @@ impl Scheduler for RRScheduler {
     }
 }
 
-pub struct StridePassInfo {
-    valid: bool,
-    stride: usize,
-    pub pass: usize,
+// 最大的步长，任意两个就绪线程的 stride 之差不超过它，
+// 因此 stride 溢出回绕后仍可以用有符号的差比较大小
+const BIG_STRIDE: u32 = 0x7fff_ffff;
+// 优先级为 1（nice 为 0）的线程的步长，nice 为 19 时的步长恰好不超过 BIG_STRIDE
+const PRIORITY_1_PASS: u32 = (BIG_STRIDE as u64 * NICE_TO_WEIGHT[39] / NICE_0_WEIGHT) as u32;
+
+struct StridePassInfo {
+    stride: u32,
+    pass: u32,
+    // 新线程的 stride 从当前的最小值开始
+    fresh: bool,
+}
+
+impl Default for StridePassInfo {
+    fn default() -> Self {
+        StridePassInfo {
+            stride: 0,
+            pass: PRIORITY_1_PASS,
+            fresh: true,
+        }
+    }
+}
+
+// a 的 stride 小于 b 的 stride，考虑溢出回绕
+fn stride_less(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+#[derive(Eq, PartialEq)]
+struct StrideKey {
+    stride: u32,
+    tid: Tid,
+}
+
+// BinaryHeap 是大根堆，stride 越小越排在前面
+impl Ord for StrideKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.stride == other.stride {
+            other.tid.cmp(&self.tid)
+        } else if stride_less(self.stride, other.stride) {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        }
+    }
+}
+
+impl PartialOrd for StrideKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 pub struct StrideScheduler {
-    pub threads: Vec<StridePassInfo>,
+    threads: Vec<StridePassInfo>,
+    heap: BinaryHeap<StrideKey>,
     current: Option<Tid>,
 }
 
@@ -113,44 +186,168 @@ impl StrideScheduler {
     pub fn new() -> Self {
         Self {
             threads: Vec::new(),
+            heap: BinaryHeap::new(),
             current: None,
         }
     }
+
+    fn info(&mut self, tid: Tid) -> &mut StridePassInfo {
+        if tid >= self.threads.len() {
+            self.threads.resize_with(tid + 1, Default::default);
+        }
+        &mut self.threads[tid]
+    }
+
+    // 就绪线程与正在运行的线程中最小的 stride
+    fn min_stride(&self) -> Option<u32> {
+        let ready = self.heap.peek().map(|key| key.stride);
+        let running = self.current.map(|tid| self.threads[tid].stride);
+        match (ready, running) {
+            (Some(a), Some(b)) if stride_less(b, a) => Some(b),
+            (Some(a), _) => Some(a),
+            (None, b) => b,
+        }
+    }
 }
 
 impl Scheduler for StrideScheduler {
     fn push(&mut self, tid: Tid) {
-        if tid >= self.threads.len() {
-            self.threads.resize_with(tid + 1, || StridePassInfo {
-                valid: false,
-                stride: 0,
-                pass: 65536,
-            });
+        let min_stride = self.min_stride();
+        let info = self.info(tid);
+        if info.fresh {
+            info.fresh = false;
+            info.stride = min_stride.unwrap_or(0);
         }
-        self.threads[tid].valid = true;
+        let stride = info.stride;
+        self.heap.push(StrideKey { stride, tid });
     }
 
     fn pop(&mut self) -> Option<Tid> {
-        let mut idx: Option<Tid> = None;
-        for i in 0..self.threads.len() {
-            if self.threads[i].valid
-                && (idx.is_none() || self.threads[i].stride < self.threads[idx.unwrap()].stride)
-            {
-                idx = Some(i);
+        let tid = self.heap.pop().map(|key| key.tid);
+        self.current = tid;
+        tid
+    }
+
+    fn tick(&mut self) -> bool {
+        if let Some(tid) = self.current {
+            let info = &mut self.threads[tid];
+            info.stride = info.stride.wrapping_add(info.pass);
+        }
+        true
+    }
+
+    fn exit(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        // tid 会被复用
+        *self.info(tid) = StridePassInfo::default();
+    }
+
+    // 优先级为 0 时视为 1
+    fn set_priority(&mut self, tid: Tid, priority: usize) {
+        let priority = priority.max(1).min(PRIORITY_1_PASS as usize) as u32;
+        self.info(tid).pass = PRIORITY_1_PASS / priority;
+    }
+
+    // nice 为 0 时相当于优先级 1，nice 为正时相当于小于 1 的优先级
+    fn set_nice(&mut self, tid: Tid, nice: isize) {
+        let pass = PRIORITY_1_PASS as u64 * NICE_0_WEIGHT / nice_to_weight(nice);
+        self.info(tid).pass = pass.min(BIG_STRIDE as u64) as u32;
+    }
+
+    // 睡眠过久的线程的 stride 落后太多，不能让它独占 CPU，也不能超出可比较的范围
+    fn on_wakeup(&mut self, tid: Tid) {
+        if let Some(min_stride) = self.min_stride() {
+            let info = self.info(tid);
+            if stride_less(info.stride, min_stride) {
+                info.stride = min_stride;
             }
         }
-        if let Some(idx) = idx {
-            self.threads[idx].valid = false;
-            self.current = Some(idx);
-            Some(idx)
-        } else {
-            None
+        self.push(tid);
+    }
+
+    fn on_block(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+    }
+}
+
+// 各级队列的时间片长度，级别越低时间片越长
+const MLFQ_SLICES: [usize; 4] = [1, 2, 4, 8];
+// 每隔多少个时钟中断将所有线程提升到最高级
+const MLFQ_BOOST_INTERVAL: usize = 100;
+
+#[derive(Default, Clone)]
+struct MLFQInfo {
+    level: usize,
+    time: usize,
+}
+
+/// 多级反馈队列：用完时间片的线程降一级，定期把所有线程提升到最高级以免饥饿
+pub struct MLFQScheduler {
+    queues: Vec<VecDeque<Tid>>,
+    threads: Vec<MLFQInfo>,
+    current: Option<Tid>,
+    ticks: usize,
+}
+
+impl MLFQScheduler {
+    pub fn new() -> Self {
+        MLFQScheduler {
+            queues: (0..MLFQ_SLICES.len()).map(|_| VecDeque::new()).collect(),
+            threads: Vec::new(),
+            current: None,
+            ticks: 0,
+        }
+    }
+
+    fn boost(&mut self) {
+        for level in 1..self.queues.len() {
+            while let Some(tid) = self.queues[level].pop_front() {
+                self.queues[0].push_back(tid);
+            }
+        }
+        for info in self.threads.iter_mut() {
+            info.level = 0;
+            info.time = info.time.min(MLFQ_SLICES[0]);
+        }
+    }
+}
+
+impl Scheduler for MLFQScheduler {
+    fn push(&mut self, tid: Tid) {
+        if tid >= self.threads.len() {
+            self.threads.resize_with(tid + 1, Default::default);
+        }
+        let info = &mut self.threads[tid];
+        // 新线程或用完时间片的线程重新获得所在级别的时间片
+        if info.time == 0 {
+            info.time = MLFQ_SLICES[info.level];
         }
+        self.queues[info.level].push_back(tid);
+    }
+
+    fn pop(&mut self) -> Option<Tid> {
+        let tid = self.queues.iter_mut().find_map(|queue| queue.pop_front());
+        self.current = tid;
+        tid
     }
 
     fn tick(&mut self) -> bool {
+        self.ticks += 1;
+        if self.ticks % MLFQ_BOOST_INTERVAL == 0 {
+            self.boost();
+        }
         if let Some(tid) = self.current {
-            self.threads[tid].stride += self.threads[tid].pass;
+            let info = &mut self.threads[tid];
+            info.time = info.time.saturating_sub(1);
+            if info.time > 0 {
+                return false;
+            }
+            // 用完了整个时间片，说明是计算密集型的线程
+            info.level = min(info.level + 1, MLFQ_SLICES.len() - 1);
         }
         true
     }
@@ -159,5 +356,173 @@ impl Scheduler for StrideScheduler {
         if self.current == Some(tid) {
             self.current = None;
         }
+        // tid 会被复用，新线程从最高级开始
+        if let Some(info) = self.threads.get_mut(tid) {
+            *info = MLFQInfo::default();
+        }
+    }
+
+    // 主动睡眠的线程保留剩余的时间片与级别
+    fn on_block(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+    }
+}
+
+pub const NICE_MIN: isize = -20;
+pub const NICE_MAX: isize = 19;
+
+// nice 值为 0 的线程的权重
+const NICE_0_WEIGHT: u64 = 1024;
+
+// 与 Linux 相同，nice 值每差 1，得到的 CPU 时间约差 10%
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+fn nice_to_weight(nice: isize) -> u64 {
+    NICE_TO_WEIGHT[(nice.max(NICE_MIN).min(NICE_MAX) - NICE_MIN) as usize]
+}
+
+// 被唤醒的线程的 vruntime 最多比 min_vruntime 小这么多个时钟中断的时间
+const CFS_SLEEPER_CREDIT: u64 = 3;
+
+struct CFSInfo {
+    vruntime: u64,
+    weight: u64,
+    // 新线程的 vruntime 从 min_vruntime 开始
+    fresh: bool,
+}
+
+impl Default for CFSInfo {
+    fn default() -> Self {
+        CFSInfo {
+            vruntime: 0,
+            weight: NICE_0_WEIGHT,
+            fresh: true,
+        }
+    }
+}
+
+/// 完全公平调度：按实际运行的时钟周期累计加权的 vruntime，总是运行 vruntime 最小的线程
+pub struct CFSScheduler {
+    threads: Vec<CFSInfo>,
+    // 就绪线程按 (vruntime, tid) 排序
+    ready: BTreeSet<(u64, Tid)>,
+    current: Option<Tid>,
+    // 当前线程上一次记账的时刻
+    start: u64,
+    // 单调不减，作为新线程与被唤醒线程的起点
+    min_vruntime: u64,
+}
+
+impl CFSScheduler {
+    pub fn new() -> Self {
+        CFSScheduler {
+            threads: Vec::new(),
+            ready: BTreeSet::new(),
+            current: None,
+            start: 0,
+            min_vruntime: 0,
+        }
+    }
+
+    fn info(&mut self, tid: Tid) -> &mut CFSInfo {
+        if tid >= self.threads.len() {
+            self.threads.resize_with(tid + 1, Default::default);
+        }
+        &mut self.threads[tid]
+    }
+
+    // 把当前线程从上次记账到现在运行的时间计入它的 vruntime
+    fn account(&mut self) {
+        if let Some(tid) = self.current {
+            let now = get_cycle();
+            let delta = now - self.start;
+            self.start = now;
+            let info = &mut self.threads[tid];
+            info.vruntime += delta * NICE_0_WEIGHT / info.weight;
+            let mut min_vruntime = info.vruntime;
+            if let Some(&(vruntime, _)) = self.ready.iter().next() {
+                min_vruntime = min(min_vruntime, vruntime);
+            }
+            self.min_vruntime = self.min_vruntime.max(min_vruntime);
+        }
+    }
+}
+
+impl Scheduler for CFSScheduler {
+    fn push(&mut self, tid: Tid) {
+        // 主动让出 CPU 的线程
+        if self.current == Some(tid) {
+            self.account();
+            self.current = None;
+        }
+        let min_vruntime = self.min_vruntime;
+        let info = self.info(tid);
+        if info.fresh {
+            info.fresh = false;
+            info.vruntime = min_vruntime;
+        }
+        let vruntime = info.vruntime;
+        self.ready.insert((vruntime, tid));
+    }
+
+    fn pop(&mut self) -> Option<Tid> {
+        let first = self.ready.iter().next().cloned();
+        let tid = first.map(|key| {
+            self.ready.remove(&key);
+            key.1
+        });
+        self.current = tid;
+        self.start = get_cycle();
+        tid
+    }
+
+    // 每个时钟中断都重新选择 vruntime 最小的线程
+    fn tick(&mut self) -> bool {
+        self.account();
+        true
+    }
+
+    fn exit(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        // tid 会被复用
+        *self.info(tid) = CFSInfo::default();
+    }
+
+    // 兼容 set_priority，优先级 p 的权重为 nice 为 0 时的 p 倍
+    fn set_priority(&mut self, tid: Tid, priority: usize) {
+        self.account();
+        self.info(tid).weight = priority.max(1) as u64 * NICE_0_WEIGHT;
+    }
+
+    fn set_nice(&mut self, tid: Tid, nice: isize) {
+        self.account();
+        self.info(tid).weight = nice_to_weight(nice);
+    }
+
+    // 睡眠不能攒下太多 vruntime，否则醒来后会长时间独占 CPU
+    fn on_wakeup(&mut self, tid: Tid) {
+        let floor = self
+            .min_vruntime
+            .saturating_sub(CFS_SLEEPER_CREDIT * TIMEBASE);
+        let info = self.info(tid);
+        if !info.fresh {
+            info.vruntime = info.vruntime.max(floor);
+        }
+        self.push(tid);
+    }
+
+    fn on_block(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.account();
+            self.current = None;
+        }
     }
 }
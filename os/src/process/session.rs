@@ -0,0 +1,88 @@
+//! Process groups and sessions, as in POSIX.
+//!
+//! Signals from the console go to a process group, the foreground group of the session
+//! owning the console. A new process leads its own group and session, a forked one joins
+//! the group and the session of its parent.
+
+use super::{current_process, Tid, THREAD_POOL};
+use crate::syscall::SysError;
+
+/// Whether process group `pgid` has a live process in session `sid`.
+pub fn group_in_session(pgid: Tid, sid: Tid) -> bool {
+    let pool = THREAD_POOL.lock();
+    pool.process_group(pgid)
+        .into_iter()
+        .filter_map(|pid| pool.find_process(pid))
+        .any(|proc| proc.lock().sid == sid)
+}
+
+/// Move process `pid` (the current one if 0) into process group `pgid` (a new one led by it if 0).
+///
+/// Only the current process and its children in the same session may be moved,
+/// into a group of the session, and a session leader can not leave its group.
+pub fn set_pgid(pid: Tid, pgid: Tid) -> Result<(), SysError> {
+    let (current, sid, is_child) = {
+        let proc = current_process().lock();
+        (proc.pid, proc.sid, proc.children.contains(&pid))
+    };
+    let pid = if pid == 0 { current } else { pid };
+    let pgid = if pgid == 0 { pid } else { pgid };
+    if pid != current && !is_child {
+        return Err(SysError::ESRCH);
+    }
+    if pgid != pid && !group_in_session(pgid, sid) {
+        return Err(SysError::EPERM);
+    }
+    let proc = THREAD_POOL
+        .lock()
+        .find_process(pid)
+        .ok_or(SysError::ESRCH)?;
+    let mut proc = proc.lock();
+    if proc.sid != sid || proc.sid == proc.pid {
+        return Err(SysError::EPERM);
+    }
+    proc.pgid = pgid;
+    Ok(())
+}
+
+/// The process group of process `pid`, the current one if 0.
+pub fn get_pgid(pid: Tid) -> Result<Tid, SysError> {
+    if pid == 0 {
+        return Ok(current_process().lock().pgid);
+    }
+    let proc = THREAD_POOL
+        .lock()
+        .find_process(pid)
+        .ok_or(SysError::ESRCH)?;
+    let pgid = proc.lock().pgid;
+    Ok(pgid)
+}
+
+/// The session of process `pid`, the current one if 0.
+pub fn get_sid(pid: Tid) -> Result<Tid, SysError> {
+    if pid == 0 {
+        return Ok(current_process().lock().sid);
+    }
+    let proc = THREAD_POOL
+        .lock()
+        .find_process(pid)
+        .ok_or(SysError::ESRCH)?;
+    let sid = proc.lock().sid;
+    Ok(sid)
+}
+
+/// Start a new session led by the current process, in a new process group.
+///
+/// The new session has no controlling terminal. A process group leader can not do it,
+/// otherwise the other processes of its group would be left in another session.
+pub fn set_sid() -> Result<Tid, SysError> {
+    let pid = current_process().lock().pid;
+    let pool = THREAD_POOL.lock();
+    if !pool.process_group(pid).is_empty() {
+        return Err(SysError::EPERM);
+    }
+    let mut proc = current_process().lock();
+    proc.pgid = pid;
+    proc.sid = pid;
+    Ok(pid)
+}
@@ -0,0 +1,324 @@
+//! POSIX signals, delivered to a process when one of its threads returns to user mode.
+//!
+//! Each process has a mask of pending signals and a mask of blocked ones, only the standard
+//! signals 1 ~ 31 are supported. A handler runs on the user stack above a `SigFrame`,
+//! and returns to its `restorer` which calls sigreturn to resume the interrupted code.
+
+use core::mem::size_of;
+
+use super::structs::Process;
+use super::{current_process, exit_if_killed, kill_current, park, Tid, THREAD_POOL};
+use crate::context::TrapFrame;
+use crate::memory::user_access::{get_user, put_user};
+use crate::syscall::SysError;
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGWINCH: usize = 28;
+
+pub const NSIG: usize = 32;
+
+// sa_handler 的两个特殊值
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+// sa_flags，与 Linux 一致
+pub const SA_NODEFER: usize = 0x4000_0000;
+pub const SA_RESETHAND: usize = 0x8000_0000;
+
+// sigprocmask 的 how 参数
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+pub type SigSet = u64;
+
+pub fn sigmask(sig: usize) -> SigSet {
+    1 << sig
+}
+
+// SIGKILL 与 SIGSTOP 不能被阻塞、忽略或处理
+const UNBLOCKABLE: SigSet = (1 << SIGKILL) | (1 << SIGSTOP);
+const STOP_SIGNALS: SigSet = (1 << SIGSTOP) | (1 << SIGTSTP) | (1 << SIGTTIN) | (1 << SIGTTOU);
+
+/// What happens to a signal without a handler
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+pub fn default_action(sig: usize) -> DefaultAction {
+    match sig {
+        SIGCHLD | SIGURG | SIGWINCH => DefaultAction::Ignore,
+        SIGCONT => DefaultAction::Continue,
+        SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => DefaultAction::Stop,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// The argument of sigaction, `restorer` is where the handler returns to.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub restorer: usize,
+    pub mask: SigSet,
+}
+
+/// The signal state of a process
+#[derive(Clone)]
+pub struct Signals {
+    pub pending: SigSet,
+    pub blocked: SigSet,
+    pub actions: [SigAction; NSIG],
+    /// The stop signal which stopped the process, its threads park on their way back to user mode
+    pub stopped: Option<usize>,
+    /// Whether the parent has been told by `wait` that it stopped
+    pub stop_reported: bool,
+}
+
+impl Default for Signals {
+    fn default() -> Self {
+        Signals {
+            pending: 0,
+            blocked: 0,
+            actions: [SigAction::default(); NSIG],
+            stopped: None,
+            stop_reported: false,
+        }
+    }
+}
+
+impl Signals {
+    /// The state of a forked child: the actions and the mask are inherited, nothing is pending.
+    pub fn fork(&self) -> Self {
+        Signals {
+            pending: 0,
+            stopped: None,
+            ..self.clone()
+        }
+    }
+
+    /// The handlers are gone after exec, the ignored signals stay ignored.
+    pub fn exec(&mut self) {
+        for action in self.actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SigAction::default();
+            }
+        }
+    }
+
+    pub fn set_blocked(&mut self, blocked: SigSet) {
+        self.blocked = blocked & !UNBLOCKABLE;
+    }
+
+    /// Whether `sig` is blocked or ignored, such a SIGTTIN makes reading the console fail.
+    pub fn discarded(&self, sig: usize) -> bool {
+        self.blocked & sigmask(sig) != 0 || self.actions[sig].handler == SIG_IGN
+    }
+
+    // 信号是否会被丢弃，没有处理函数的 SIGCONT 只让进程继续运行
+    fn ignored(&self, sig: usize) -> bool {
+        match self.actions[sig].handler {
+            SIG_IGN => true,
+            SIG_DFL => match default_action(sig) {
+                DefaultAction::Ignore | DefaultAction::Continue => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Post signal `sig` to `proc`, returns whether its threads should be woken up.
+///
+/// A signal which terminates the process by default kills it at once,
+/// so that a thread sleeping in the kernel does not have to return to user mode first.
+fn post(proc: &mut Process, sig: usize) -> bool {
+    if sig == SIGKILL {
+        proc.killed = Some(128 + sig);
+        return true;
+    }
+    let signals = &mut proc.signals;
+    // SIGCONT 与停止信号互相抵消
+    if sig == SIGCONT {
+        signals.stopped = None;
+        signals.pending &= !STOP_SIGNALS;
+    } else if sigmask(sig) & STOP_SIGNALS != 0 {
+        signals.pending &= !sigmask(SIGCONT);
+    }
+    if signals.ignored(sig) {
+        return sig == SIGCONT;
+    }
+    let terminate = signals.actions[sig].handler == SIG_DFL
+        && default_action(sig) == DefaultAction::Terminate
+        && signals.blocked & sigmask(sig) == 0;
+    if terminate {
+        proc.killed = Some(128 + sig);
+    } else {
+        proc.signals.pending |= sigmask(sig);
+    }
+    true
+}
+
+/// Send signal `sig` to process `pid`, signal 0 only checks that the process exists.
+pub fn send(pid: Tid, sig: usize) -> Result<(), SysError> {
+    if sig >= NSIG {
+        return Err(SysError::EINVAL);
+    }
+    let proc = THREAD_POOL
+        .lock()
+        .find_process(pid)
+        .ok_or(SysError::ESRCH)?;
+    let wake = {
+        let mut proc = proc.lock();
+        // 内核线程不接收信号，已退出的进程忽略信号
+        if proc.vm.is_none() && !proc.threads.is_empty() {
+            return Err(SysError::EPERM);
+        }
+        sig != 0 && !proc.threads.is_empty() && post(&mut proc, sig)
+    };
+    if wake {
+        // 目标进程可能已在别的 hart 上退出并被回收
+        let mut pool = THREAD_POOL.lock();
+        if pool.find_process(pid).is_some() {
+            pool.wakeup_process(pid);
+        }
+    }
+    Ok(())
+}
+
+/// Send signal `sig` to all the processes in process group `pgid`.
+pub fn send_group(pgid: Tid, sig: usize) -> Result<(), SysError> {
+    let pids = THREAD_POOL.lock().process_group(pgid);
+    if pids.is_empty() {
+        return Err(SysError::ESRCH);
+    }
+    for pid in pids {
+        // 进程可能刚刚退出
+        send(pid, sig).ok();
+    }
+    Ok(())
+}
+
+/// Whether the current process is killed or has a signal to handle.
+///
+/// A blocking syscall fails with EINTR then, so that the signal is handled on returning to user mode.
+pub fn interrupted() -> bool {
+    let proc = current_process().lock();
+    proc.killed.is_some() || proc.signals.pending & !proc.signals.blocked != 0
+}
+
+/// Saved on the user stack while a handler runs
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct SigFrame {
+    x: [usize; 32],
+    sepc: usize,
+    blocked: SigSet,
+}
+
+/// Handle the pending signals of the current process, called before returning to user mode.
+///
+/// At most one handler is set up each time, it runs once `tf` is restored.
+pub fn do_signal(tf: &mut TrapFrame) {
+    loop {
+        exit_if_killed();
+        let mut proc = current_process().lock();
+        if proc.signals.stopped.is_some() {
+            drop(proc);
+            // SIGCONT 与 SIGKILL 会唤醒停止的进程
+            park();
+            continue;
+        }
+        let signals = &mut proc.signals;
+        let deliverable = signals.pending & !signals.blocked;
+        if deliverable == 0 {
+            return;
+        }
+        let sig = deliverable.trailing_zeros() as usize;
+        signals.pending &= !sigmask(sig);
+        let action = signals.actions[sig];
+        match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL => match default_action(sig) {
+                DefaultAction::Terminate => {
+                    drop(proc);
+                    kill_current(sig);
+                }
+                DefaultAction::Stop => {
+                    signals.stopped = Some(sig);
+                    signals.stop_reported = false;
+                    // 让等待中的父进程知道它停止了
+                    let pid = proc.pid;
+                    drop(proc);
+                    THREAD_POOL.lock().wakeup_parent(pid);
+                }
+                DefaultAction::Ignore | DefaultAction::Continue => {}
+            },
+            handler => {
+                let frame = SigFrame {
+                    x: tf.x,
+                    sepc: tf.sepc,
+                    blocked: signals.blocked,
+                };
+                if action.flags & SA_NODEFER == 0 {
+                    signals.blocked |= sigmask(sig);
+                }
+                signals.set_blocked(signals.blocked | action.mask);
+                if action.flags & SA_RESETHAND != 0 {
+                    signals.actions[sig] = SigAction::default();
+                }
+                drop(proc);
+                // 栈上放不下信号帧时杀死进程，sp 由用户程序设置，可能任意小
+                let sp = match tf.x[2].checked_sub(size_of::<SigFrame>()) {
+                    Some(sp) => sp & !0xf,
+                    None => return kill_current(SIGSEGV),
+                };
+                if put_user(sp as *mut SigFrame, frame).is_err() {
+                    kill_current(SIGSEGV);
+                }
+                tf.x[2] = sp;
+                tf.x[1] = action.restorer;
+                tf.x[10] = sig;
+                tf.sepc = handler;
+                return;
+            }
+        }
+    }
+}
+
+/// Return from a handler, `tf` is restored from the signal frame at the user stack pointer.
+///
+/// Returns the a0 of the interrupted code, so that the syscall return value does not change it.
+pub fn sigreturn(tf: &mut TrapFrame) -> Result<isize, SysError> {
+    let frame = get_user(tf.x[2] as *const SigFrame)?;
+    tf.x = frame.x;
+    tf.sepc = frame.sepc;
+    current_process().lock().signals.set_blocked(frame.blocked);
+    Ok(frame.x[10] as isize)
+}
@@ -7,30 +7,120 @@ use xmas_elf::{
 };
 
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 use crate::alloc::alloc::{alloc, dealloc, Layout};
 use crate::consts::*;
 use crate::context::{Context, TrapFrame};
 use crate::fs::file::File;
 use crate::memory::memory_set::{attr::MemoryAttr, handler::ByFrame, MemorySet};
+use crate::syscall::SysError;
+use crate::timer::get_cycle;
 
-use super::Tid;
+use super::signal::Signals;
+use super::timer::TimerId;
+use super::{ExitCode, Tid};
 
 #[derive(Clone)]
 pub enum Status {
     Ready,
     Running(Tid),
     Sleeping,
-    // Exited(ExitCode),
+    Zombie,
 }
 
 pub struct Thread {
+    pub tid: Tid,
     pub context: Context,
     pub kstack: KernelStack,
-    pub wait: Option<Tid>,
+    pub proc: Arc<Mutex<Process>>,
+    pub usage: CpuUsage,
+    /// The name of a kernel thread, shown when it exits; empty for user threads
+    pub name: String,
+}
+
+/// CPU time in cycles and context switches, counted by the running thread itself
+#[derive(Default, Copy, Clone, Debug)]
+pub struct CpuUsage {
+    pub utime: u64,
+    pub stime: u64,
+    /// Switched out by yielding or sleeping
+    pub nvcsw: usize,
+    /// Switched out by preemption
+    pub nivcsw: usize,
+    // 上一次计时的时间
+    since: u64,
+}
+
+impl CpuUsage {
+    /// Start timing, called when the thread is switched in.
+    pub fn start(&mut self) {
+        self.since = get_cycle();
+    }
+
+    /// Charge the time since the last call to user mode if `user`, otherwise to kernel mode.
+    pub fn charge(&mut self, user: bool) {
+        let now = get_cycle();
+        if user {
+            self.utime += now - self.since;
+        } else {
+            self.stime += now - self.since;
+        }
+        self.since = now;
+    }
+
+    pub fn add(&mut self, other: &CpuUsage) {
+        self.utime += other.utime;
+        self.stime += other.stime;
+        self.nvcsw += other.nvcsw;
+        self.nivcsw += other.nivcsw;
+    }
+}
+
+/// Per-thread statistics, also summed up for processes
+#[derive(Default, Copy, Clone, Debug)]
+pub struct ThreadStats {
+    /// Deadlines missed as a SCHED_DEADLINE thread
+    pub deadline_misses: usize,
+    /// Times woken up from sleeping
+    pub wakeups: usize,
+    pub usage: CpuUsage,
+}
+
+impl ThreadStats {
+    pub fn add(&mut self, other: &ThreadStats) {
+        self.deadline_misses += other.deadline_misses;
+        self.wakeups += other.wakeups;
+        self.usage.add(&other.usage);
+    }
+}
+
+/// Resources shared by all threads of a process.
+///
+/// The pid of a process is the tid of its first thread.
+pub struct Process {
+    pub pid: Tid,
+    /// The process group, 0 before the first thread is added means a new group led by itself
+    pub pgid: Tid,
+    /// The session, 0 before the first thread is added means a new session led by itself
+    pub sid: Tid,
+    pub parent: Option<Tid>,
+    pub children: Vec<Tid>,
+    pub threads: Vec<Tid>,
     pub vm: Option<Arc<Mutex<MemorySet>>>,
     pub ofile: [Option<Arc<Mutex<File>>>; NOFILE],
+    pub cwd: String,
+    /// Set when the process is killed, its remaining threads exit with this code
+    pub killed: Option<ExitCode>,
+    pub signals: Signals,
+    /// The real-time interval timer set by setitimer and alarm, not inherited by a forked child
+    pub real_timer: Option<TimerId>,
+    /// Statistics of the exited threads
+    pub stats: ThreadStats,
+    /// Statistics of the reaped children, including their own children
+    pub children_stats: ThreadStats,
 }
 
 impl Thread {
@@ -40,26 +130,28 @@ impl Thread {
         }
     }
 
-    pub fn new_kernel(entry: usize) -> Box<Thread> {
+    pub fn new_kernel(entry: usize, name: &str) -> Box<Thread> {
         unsafe {
             let kstack_ = KernelStack::new();
             Box::new(Thread {
+                tid: 0,
                 context: Context::new_kernel_thread(entry, kstack_.top(), satp::read().bits()),
                 kstack: kstack_,
-                wait: None,
-                vm: None,
-                ofile: [None; NOFILE],
+                proc: Process::new_kernel(),
+                usage: CpuUsage::default(),
+                name: String::from(name),
             })
         }
     }
 
     pub fn get_boot_thread() -> Box<Thread> {
         Box::new(Thread {
+            tid: 0,
             context: Context::null(),
             kstack: KernelStack::new_empty(),
-            wait: None,
-            vm: None,
-            ofile: [None; NOFILE],
+            proc: Process::new_kernel(),
+            usage: CpuUsage::default(),
+            name: String::new(),
         })
     }
 
@@ -69,85 +161,248 @@ impl Thread {
         }
     }
 
-    pub unsafe fn new_user(data: &[u8], wait_thread: Option<Tid>) -> Box<Thread> {
-        let elf = ElfFile::new(data).expect("failed to analyse elf!");
-
-        match elf.header.pt2.type_().as_type() {
-            header::Type::Executable => {
-                // println!("it really a executable!");
-            }
-            header::Type::SharedObject => {
-                panic!("shared object is not supported!");
-            }
-            _ => {
-                panic!("unsupported elf type!");
+    /// Create the first thread of a new process, with `args` and `envs` on its user stack
+    ///
+    /// Fails with ENOEXEC if `data` is not an executable ELF file.
+    pub unsafe fn new_user(
+        data: &[u8],
+        args: &[String],
+        envs: &[String],
+        parent: Option<Tid>,
+    ) -> Result<Box<Thread>, SysError> {
+        let (vm, entry_addr, ustack_top, regs) = load_program(data, args, envs)?;
+        let kstack = KernelStack::new();
+        let context =
+            Context::new_user_thread(entry_addr, ustack_top, kstack.top(), vm.token(), regs);
+        let mut proc = Process::new(parent);
+        proc.vm = Some(Arc::new(Mutex::new(vm)));
+        // 0、1、2 分别为标准输入、标准输出与标准错误输出
+        for i in 0..3 {
+            let mut file = File::default();
+            if i == 0 {
+                file.open_stdin();
+            } else {
+                file.open_stdout();
             }
+            proc.ofile[i] = Some(Arc::new(Mutex::new(file)));
         }
-        let entry_addr = elf.header.pt2.entry_point() as usize;
-        let mut vm = elf.make_memory_set();
-
-        let ustack_top = {
-            let (ustack_bottom, ustack_top) =
-                (USER_STACK_OFFSET, USER_STACK_OFFSET + USER_STACK_SIZE);
-            vm.push(
-                ustack_bottom,
-                ustack_top,
-                MemoryAttr::new().set_user(),
-                ByFrame::new(),
-                None,
-            );
-            ustack_top
-        };
+        Ok(Box::new(Thread {
+            tid: 0,
+            context,
+            kstack: kstack,
+            proc: Arc::new(Mutex::new(proc)),
+            usage: CpuUsage::default(),
+            name: String::new(),
+        }))
+    }
 
+    /// Create a new thread in the same process, running `entry(arg)` on the user stack `ustack_top`
+    pub fn new_clone(&self, entry: usize, ustack_top: usize, arg: usize) -> Box<Thread> {
         let kstack = KernelStack::new();
-
-        let mut thread = Thread {
-            context: Context::new_user_thread(entry_addr, ustack_top, kstack.top(), vm.token()),
-            kstack: kstack,
-            wait: wait_thread,
-            vm: Some(Arc::new(Mutex::new(vm))),
-            ofile: [None; NOFILE],
+        let vm_token = self.proc.lock().vm.as_ref().unwrap().lock().token();
+        let context = unsafe {
+            Context::new_user_thread(entry, ustack_top, kstack.top(), vm_token, [arg, 0, 0])
         };
-        for i in 0..3 {
-            thread.ofile[i] = Some(Arc::new(Mutex::new(File::default())));
-        }
-        Box::new(thread)
+        Box::new(Thread {
+            tid: 0,
+            context,
+            kstack,
+            proc: self.proc.clone(),
+            usage: CpuUsage::default(),
+            name: String::new(),
+        })
     }
 
-    // 分配文件描述符
-    pub fn alloc_fd(&mut self) -> i32 {
-        let mut fd = 0;
-        for i in 0usize..NOFILE {
-            if self.ofile[i].is_none() {
-                fd = i;
-                break;
+    /// Replace the program of the current process with `data`, as if it was started by `new_user`.
+    ///
+    /// The pid, the tid and the file descriptors without close-on-exec are kept.
+    /// On failure the current program is left untouched.
+    pub unsafe fn exec(
+        &mut self,
+        data: &[u8],
+        args: &[String],
+        envs: &[String],
+        tf: &mut TrapFrame,
+    ) -> Result<(), SysError> {
+        let (vm, entry_addr, ustack_top, regs) = load_program(data, args, envs)?;
+        vm.activate_quietly();
+        let mut closed = Vec::new();
+        let mut proc = self.proc.lock();
+        proc.vm = Some(Arc::new(Mutex::new(vm)));
+        proc.signals.exec();
+        for file in proc.ofile.iter_mut() {
+            if file.as_ref().map_or(false, |f| f.lock().get_cloexec()) {
+                closed.push(file.take());
             }
         }
-        self.ofile[fd] = Some(Arc::new(Mutex::new(File::default())));
-        fd as i32
-    }
-    // 回收文件描述符
-    pub fn dealloc_fd(&mut self, fd: i32) {
-        assert!(self.ofile[fd as usize].is_some());
-        self.ofile[fd as usize] = None;
+        drop(proc);
+        drop(closed);
+        // 从新程序的入口开始执行，sstatus 保持不变
+        tf.x = [0; 32];
+        tf.x[2] = ustack_top;
+        tf.x[10] = regs[0];
+        tf.x[11] = regs[1];
+        tf.x[12] = regs[2];
+        tf.sepc = entry_addr;
+        Ok(())
     }
 
     /// Fork a new process from current one
     pub fn fork(&self, tf: &TrapFrame) -> Box<Thread> {
         let kstack = KernelStack::new(); // 分配新的栈
-        let vm = self.vm.as_ref().unwrap().lock().clone(); // 为变量分配内存，将虚拟地址映射到新的内存上（尚未实现）
+        let proc = self.proc.lock();
+        let vm = proc.vm.as_ref().unwrap().lock().clone(); // 为变量分配内存，将虚拟地址映射到新的内存上（尚未实现）
         let vm_token = vm.token();
         let context = unsafe { Context::new_fork(tf, kstack.top(), vm_token) }; // 复制上下文到 kernel stack 上（尚未实现）
+        let mut new_proc = Process::new(Some(proc.pid));
+        new_proc.pgid = proc.pgid;
+        new_proc.sid = proc.sid;
+        new_proc.vm = Some(Arc::new(Mutex::new(vm)));
+        new_proc.ofile = proc.ofile.clone();
+        new_proc.cwd = proc.cwd.clone();
+        new_proc.signals = proc.signals.fork();
         Box::new(Thread {
+            tid: 0,
             context,
             kstack,
-            wait: self.wait.clone(),
-            vm: Some(Arc::new(Mutex::new(vm))),
-            ofile: self.ofile.clone(),
+            proc: Arc::new(Mutex::new(new_proc)),
+            usage: CpuUsage::default(),
+            name: String::new(),
         })
     }
 }
 
+impl Process {
+    pub fn new(parent: Option<Tid>) -> Self {
+        Process {
+            pid: 0,
+            pgid: 0,
+            sid: 0,
+            parent,
+            children: Vec::new(),
+            threads: Vec::new(),
+            vm: None,
+            ofile: [None; NOFILE],
+            cwd: String::from("/"),
+            killed: None,
+            signals: Signals::default(),
+            real_timer: None,
+            stats: ThreadStats::default(),
+            children_stats: ThreadStats::default(),
+        }
+    }
+
+    fn new_kernel() -> Arc<Mutex<Process>> {
+        Arc::new(Mutex::new(Process::new(None)))
+    }
+
+    // 分配文件描述符，已满时返回 None
+    pub fn alloc_fd(&mut self) -> Option<i32> {
+        let fd = (0..NOFILE).find(|&i| self.ofile[i].is_none())?;
+        self.ofile[fd] = Some(Arc::new(Mutex::new(File::default())));
+        Some(fd as i32)
+    }
+    // 回收文件描述符，返回的文件应在释放进程的锁之后再 drop
+    pub fn dealloc_fd(&mut self, fd: i32) -> Arc<Mutex<File>> {
+        self.ofile[fd as usize].take().expect("fd not allocated")
+    }
+
+    /// Release the resources of an exited process, only the process tree is kept for `wait`.
+    ///
+    /// The resources are returned, drop them after the lock of the process is released,
+    /// since closing a pipe wakes up the threads waiting on it.
+    pub fn exit(
+        &mut self,
+    ) -> (
+        Option<Arc<Mutex<MemorySet>>>,
+        [Option<Arc<Mutex<File>>>; NOFILE],
+    ) {
+        let ofile = core::mem::replace(&mut self.ofile, [None; NOFILE]);
+        (self.vm.take(), ofile)
+    }
+}
+
+/// Build the address space of the ELF program `data`, with `args` and `envs` on its user stack.
+///
+/// Returns the address space, the entry point, the user stack top and the initial a0 ~ a2.
+/// Fails with ENOEXEC if `data` is not an executable ELF file, nothing is mapped yet then.
+/// Fails with E2BIG if `args` and `envs` take more than ARG_MAX bytes on the user stack.
+unsafe fn load_program(
+    data: &[u8],
+    args: &[String],
+    envs: &[String],
+) -> Result<(MemorySet, usize, usize, [usize; 3]), SysError> {
+    if args_size(args, envs) > ARG_MAX {
+        return Err(SysError::E2BIG);
+    }
+    let elf = ElfFile::new(data).map_err(|_| SysError::ENOEXEC)?;
+    // 只支持静态链接的可执行文件，不支持共享库
+    if elf.header.pt2.type_().as_type() != header::Type::Executable {
+        return Err(SysError::ENOEXEC);
+    }
+    let entry_addr = elf.header.pt2.entry_point() as usize;
+    let mut vm = elf.make_memory_set()?;
+
+    let ustack_top = {
+        let (ustack_bottom, ustack_top) = (USER_STACK_OFFSET, USER_STACK_OFFSET + USER_STACK_SIZE);
+        vm.push(
+            ustack_bottom,
+            ustack_top,
+            MemoryAttr::new().set_user(),
+            ByFrame::new(),
+            None,
+        );
+        ustack_top
+    };
+    let (ustack_top, argv, envp) = push_args(&mut vm, ustack_top, args, envs);
+    Ok((vm, entry_addr, ustack_top, [args.len(), argv, envp]))
+}
+
+// 参数与环境变量在用户栈上占用的大小，包括两个空指针
+fn args_size(args: &[String], envs: &[String]) -> usize {
+    let ptr_size = core::mem::size_of::<usize>();
+    let strs_len: usize = args.iter().chain(envs).map(|s| s.len() + 1).sum();
+    (args.len() + envs.len() + 2) * ptr_size + strs_len
+}
+
+/// Push `args` and `envs` onto the user stack of `vm` whose top is `ustack_top`.
+///
+/// The layout from the new stack top is: argv[], NULL, envp[], NULL, and then the strings.
+/// Returns the new stack top and the addresses of argv and envp.
+/// The size must have been checked against ARG_MAX.
+fn push_args(
+    vm: &mut MemorySet,
+    ustack_top: usize,
+    args: &[String],
+    envs: &[String],
+) -> (usize, usize, usize) {
+    let ptr_size = core::mem::size_of::<usize>();
+    let ptrs_len = (args.len() + envs.len() + 2) * ptr_size;
+    let sp = (ustack_top - args_size(args, envs)) & !0xf;
+
+    let mut ptrs: Vec<usize> = Vec::new();
+    let mut strs: Vec<u8> = Vec::new();
+    let str_base = sp + ptrs_len;
+    let mut push_strs = |list: &[String]| {
+        for s in list {
+            ptrs.push(str_base + strs.len());
+            strs.extend_from_slice(s.as_bytes());
+            strs.push(0);
+        }
+        ptrs.push(0);
+    };
+    push_strs(args);
+    push_strs(envs);
+
+    let mut data: Vec<u8> = Vec::new();
+    for ptr in ptrs {
+        data.extend_from_slice(&ptr.to_ne_bytes());
+    }
+    data.extend_from_slice(&strs);
+    vm.write_bytes(sp, &data);
+    (sp, sp, sp + (args.len() + 1) * ptr_size)
+}
+
 pub struct KernelStack(usize);
 impl KernelStack {
     pub fn new() -> Self {
@@ -178,11 +433,11 @@ impl Drop for KernelStack {
 }
 
 trait ElfExt {
-    fn make_memory_set(&self) -> MemorySet;
+    fn make_memory_set(&self) -> Result<MemorySet, SysError>;
 }
 
 impl ElfExt for ElfFile<'_> {
-    fn make_memory_set(&self) -> MemorySet {
+    fn make_memory_set(&self) -> Result<MemorySet, SysError> {
         let mut memory_set = MemorySet::new();
         for ph in self.program_iter() {
             if ph.get_type() != Ok(Type::Load) {
@@ -190,9 +445,9 @@ impl ElfExt for ElfFile<'_> {
             }
             let vaddr = ph.virtual_addr() as usize;
             let mem_size = ph.mem_size() as usize;
-            let data = match ph.get_data(self).unwrap() {
-                SegmentData::Undefined(data) => data,
-                _ => unreachable!(),
+            let data = match ph.get_data(self) {
+                Ok(SegmentData::Undefined(data)) => data,
+                _ => return Err(SysError::ENOEXEC),
             };
 
             memory_set.push(
@@ -203,7 +458,7 @@ impl ElfExt for ElfFile<'_> {
                 Some((data.as_ptr() as usize, data.len())),
             );
         }
-        memory_set
+        Ok(memory_set)
     }
 }
 
@@ -213,6 +468,10 @@ trait ToMemoryAttr {
 impl ToMemoryAttr for Flags {
     fn to_attr(&self) -> MemoryAttr {
         let mut flags = MemoryAttr::new().set_user();
+        // 不可写的段（.text 与 .rodata）映射为只读，内核也不能替用户写入
+        if !self.is_write() {
+            flags = flags.set_readonly();
+        }
         if self.is_execute() {
             flags = flags.set_execute();
         }
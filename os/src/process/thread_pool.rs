@@ -1,90 +1,510 @@
-use crate::alloc::{boxed::Box, vec::Vec};
-use crate::process::scheduler::{Scheduler, StrideScheduler};
+use spin::Mutex;
+
+use crate::alloc::{boxed::Box, sync::Arc, vec::Vec};
+use crate::process::realtime::{Policy, RealtimeScheduler};
+use crate::process::scheduler::{Scheduler, NICE_MAX, NICE_MIN};
 use crate::process::structs::*;
-use crate::process::Tid;
+use crate::process::{ExitCode, Tid};
+use crate::smp::{hart_id, online_mask, send_ipi, IPI_RESCHED};
+use crate::syscall::SysError;
 
 pub struct ThreadInfo {
+    // 每分配一次 tid 加一，区分先后使用同一个 tid 的线程
+    pub generation: usize,
     pub status: Status,
+    pub exit_code: ExitCode,
+    pub nice: isize,
+    // uCore 的 set_priority 设置的优先级，0 表示没有设置过
+    pub priority: usize,
+    pub stats: ThreadStats,
+    // 线程所在的运行队列属于哪个 hart，也是它最近运行的 hart
+    pub hart: usize,
+    // 线程在 park 之前就被唤醒了，这次 park 直接返回
+    pub wakeup_pending: bool,
+    // 线程已经 park，正在切换回 idle 线程
+    pub parking: bool,
+    pub proc: Arc<Mutex<Process>>,
     pub thread: Option<Box<Thread>>,
 }
 
+/// A thread to be woken up later, kept by timers and wait queues.
+///
+/// The tid may be reused once the thread exits, the generation tells the new thread apart.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct WakeupHandle {
+    pub tid: Tid,
+    pub generation: usize,
+}
+
+pub enum WaitResult {
+    Exited(Tid, ExitCode),
+    /// Stopped by the signal, reported only once
+    Stopped(Tid, usize),
+    Running,
+    NotFound,
+}
+
+/// All the threads, shared by the harts.
+///
+/// Each hart has its own run queues, a thread becoming ready is put on the least loaded hart.
+/// Real-time threads stay on the hart where they became real-time.
+///
+/// The thread table grows when it is mostly in use, tids are handed out round-robin
+/// so that a freed tid is not reused at once.
 pub struct ThreadPool {
     pub threads: Vec<Option<ThreadInfo>>,
-    pub scheduler: Box<StrideScheduler>,
+    // 下一次从这里开始找空闲的 tid
+    next_tid: Tid,
+    generation: usize,
+    pub schedulers: Vec<Box<dyn Scheduler>>,
+    // 实时线程由它调度，优先于 schedulers
+    pub rt: Vec<RealtimeScheduler>,
+    // 每个 hart 普通调度器中就绪的线程数，实时线程由 rt 自己计数
+    ready: Vec<usize>,
+    running: Vec<Option<Tid>>,
 }
 
 impl ThreadPool {
-    pub fn new(size: usize, scheduler: Box<StrideScheduler>) -> ThreadPool {
+    pub fn new(size: usize, schedulers: Vec<Box<dyn Scheduler>>) -> ThreadPool {
+        let harts = schedulers.len();
         ThreadPool {
             threads: {
                 let mut v = Vec::new();
                 v.resize_with(size, Default::default);
                 v
             },
-            scheduler,
+            next_tid: 0,
+            generation: 0,
+            schedulers,
+            rt: (0..harts).map(|_| RealtimeScheduler::new()).collect(),
+            ready: alloc::vec![0; harts],
+            running: alloc::vec![None; harts],
         }
     }
-    fn alloc_tid(&self) -> Tid {
-        for (i, info) in self.threads.iter().enumerate() {
-            if info.is_none() {
-                return i;
-            }
+
+    fn alloc_tid(&mut self) -> Tid {
+        let len = self.threads.len();
+        let free = |i: &Tid| self.threads[*i].is_none();
+        // 一半以上的 tid 空闲时才从头找，否则扩大线程表
+        let wrap = self.threads.iter().filter(|info| info.is_none()).count() * 2 >= len;
+        let tid = (self.next_tid..len)
+            .find(free)
+            .or_else(|| {
+                if wrap {
+                    (0..self.next_tid).find(free)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(len);
+        if tid == len {
+            self.threads.push(None);
         }
-        panic!("alloc tid failed!");
+        self.next_tid = tid + 1;
+        tid
     }
 
-    pub fn add(&mut self, _thread: Box<Thread>) -> usize {
+    pub fn add(&mut self, mut thread: Box<Thread>) -> usize {
         let tid = self.alloc_tid();
+        thread.tid = tid;
+        {
+            let mut proc = thread.proc.lock();
+            // 进程中第一个线程的 tid 即为进程的 pid
+            if proc.threads.is_empty() {
+                proc.pid = tid;
+                // 没有从父进程继承进程组与会话的新进程自成一组、自成一个会话
+                if proc.pgid == 0 {
+                    proc.pgid = tid;
+                }
+                if proc.sid == 0 {
+                    proc.sid = tid;
+                }
+            }
+            proc.threads.push(tid);
+        }
+        self.generation += 1;
         self.threads[tid] = Some(ThreadInfo {
+            generation: self.generation,
             status: Status::Ready,
-            thread: Some(_thread),
+            exit_code: 0,
+            nice: 0,
+            priority: 0,
+            stats: ThreadStats::default(),
+            hart: hart_id(),
+            wakeup_pending: false,
+            parking: false,
+            proc: thread.proc.clone(),
+            thread: Some(thread),
         });
-        self.scheduler.push(tid);
+        self.push(tid, false);
         tid
     }
 
-    pub fn acquire(&mut self) -> Option<(Tid, Box<Thread>)> {
-        if let Some(tid) = self.scheduler.pop() {
-            let mut thread_info = self.threads[tid].as_mut().expect("thread not exist!");
-            thread_info.status = Status::Running(tid);
-            return Some((tid, thread_info.thread.take().expect("thread not exist!")));
+    // 负载最轻的 hart，负载相同时优先选 prefer
+    fn least_loaded(&self, prefer: usize) -> usize {
+        let load = |hart: usize| {
+            self.ready[hart] + self.rt[hart].ready_count() + self.running[hart].is_some() as usize
+        };
+        let online = online_mask() | (1 << prefer);
+        (0..self.schedulers.len())
+            .filter(|&hart| online & (1 << hart) != 0)
+            .min_by_key(|&hart| (load(hart), hart != prefer))
+            .unwrap()
+    }
+
+    // 将就绪的线程放入运行队列，woken 表示它刚从睡眠中醒来
+    fn push(&mut self, tid: Tid, woken: bool) {
+        let (old, nice, priority) = {
+            let info = self.threads[tid].as_ref().expect("thread not exist");
+            (info.hart, info.nice, info.priority)
+        };
+        let realtime = self.rt[old].contains(tid);
+        let hart = if realtime {
+            old
+        } else {
+            self.least_loaded(old)
+        };
+        if realtime {
+            if woken {
+                self.rt[hart].on_wakeup(tid);
+            } else {
+                self.rt[hart].push(tid);
+            }
         } else {
-            return None;
+            // 迁移到另一个 hart，原来调度器中的状态作废
+            if hart != old {
+                self.schedulers[old].exit(tid);
+                self.schedulers[hart].set_nice(tid, nice);
+                if priority != 0 {
+                    self.schedulers[hart].set_priority(tid, priority);
+                }
+                self.threads[tid].as_mut().unwrap().hart = hart;
+            }
+            if woken {
+                self.schedulers[hart].on_wakeup(tid);
+            } else {
+                self.schedulers[hart].push(tid);
+            }
+            self.ready[hart] += 1;
         }
+        // 叫醒空闲的 hart，或让它的实时线程抢占普通线程
+        if hart != hart_id() && (self.running[hart].is_none() || realtime) {
+            send_ipi(1 << hart, IPI_RESCHED);
+        }
+    }
+
+    pub fn acquire(&mut self, hart: usize) -> Option<(Tid, Box<Thread>)> {
+        let tid = match self.rt[hart].pop() {
+            Some(tid) => tid,
+            None => {
+                let tid = self.schedulers[hart].pop()?;
+                self.ready[hart] -= 1;
+                tid
+            }
+        };
+        self.running[hart] = Some(tid);
+        let mut thread_info = self.threads[tid].as_mut().expect("thread not exist!");
+        thread_info.status = Status::Running(tid);
+        Some((tid, thread_info.thread.take().expect("thread not exist!")))
     }
 
+    /// Put back the thread `tid` after it is switched out on the current hart.
     pub fn retrieve(&mut self, tid: Tid, thread: Box<Thread>) {
+        self.running[hart_id()] = None;
         if self.threads[tid].is_none() {
             return;
         }
         let mut thread_info = self.threads[tid].as_mut().expect("thread not exist!");
-        thread_info.thread = Some(thread);
-        if let Status::Running(_) = thread_info.status {
-            thread_info.status = Status::Ready;
-            self.scheduler.push(tid);
+        // 其他线程读取的是它上一次切换出去时的统计
+        thread_info.stats.usage = thread.usage;
+        match thread_info.status {
+            Status::Running(_) => {
+                thread_info.thread = Some(thread);
+                if thread_info.parking {
+                    thread_info.parking = false;
+                    // 在切换出去之前已被唤醒的线程不必睡眠
+                    if !thread_info.wakeup_pending {
+                        thread_info.status = Status::Sleeping;
+                        let hart = thread_info.hart;
+                        if self.rt[hart].contains(tid) {
+                            self.rt[hart].on_block(tid);
+                        } else {
+                            self.schedulers[hart].on_block(tid);
+                        }
+                        return;
+                    }
+                    thread_info.wakeup_pending = false;
+                }
+                thread_info.status = Status::Ready;
+                self.push(tid, false);
+            }
+            Status::Zombie => {
+                // 主线程要保留到进程被回收，除非没有人会等待它
+                let (reap, parent) = {
+                    let proc = thread.proc.lock();
+                    let exited = proc.pid == tid && proc.threads.is_empty();
+                    let reap = proc.pid != tid || (exited && proc.parent.is_none());
+                    (reap, if exited { proc.parent } else { None })
+                };
+                if reap {
+                    self.threads[tid] = None;
+                    return;
+                }
+                thread_info.thread = Some(thread);
+                // 父进程可能在主线程切换出去之前就来回收过，让它再来一次
+                if let Some(parent) = parent {
+                    self.wakeup_process(parent);
+                }
+            }
+            _ => thread_info.thread = Some(thread),
+        }
+    }
+
+    pub fn tick(&mut self, hart: usize) -> bool {
+        // 实时线程正在运行时普通调度器不计时
+        let rt_running = self.rt[hart].current().is_some();
+        let ret = self.rt[hart].tick();
+        if rt_running {
+            ret
+        } else {
+            self.schedulers[hart].tick() || ret
+        }
+    }
+
+    /// When the next period of a throttled real-time thread on `hart` begins.
+    pub fn next_release(&self, hart: usize) -> Option<u64> {
+        self.rt[hart].next_release()
+    }
+
+    /// Whether a real-time thread is waiting while a normal thread runs on `hart`.
+    pub fn need_resched(&self, hart: usize) -> bool {
+        self.rt[hart].current().is_none() && self.rt[hart].has_ready()
+    }
+
+    pub fn exit(&mut self, tid: Tid, code: ExitCode) {
+        let proc = self.threads[tid].as_mut().expect("thread not exist");
+        proc.status = Status::Zombie;
+        proc.exit_code = code;
+        let hart = proc.hart;
+        proc.stats.deadline_misses += self.rt[hart].exit(tid);
+        self.schedulers[hart].exit(tid);
+    }
+
+    /// Called when the last thread of process `pid` exited with `code`.
+    pub fn exit_process(&mut self, pid: Tid, code: ExitCode) {
+        let parent = self.process(pid).lock().parent;
+        let info = self.threads[pid].as_mut().expect("process not exist");
+        info.exit_code = code;
+        match parent {
+            Some(parent) => self.wakeup_process(parent),
+            // nobody is going to wait for it, a running main thread is reaped in `retrieve`
+            None if info.thread.is_some() => self.threads[pid] = None,
+            None => {}
+        }
+    }
+
+    /// Reap one of the zombie children of `parent`, the one whose pid is `pid` or any if `pid` is 0.
+    ///
+    /// The statistics of the reaped child are added to the parent.
+    /// If `untraced`, a child stopped since the last report is also returned.
+    pub fn try_wait(&mut self, parent: &mut Process, pid: Tid, untraced: bool) -> WaitResult {
+        let mut result = WaitResult::NotFound;
+        let children = &mut parent.children;
+        for (i, &child) in children.iter().enumerate() {
+            if pid != 0 && child != pid {
+                continue;
+            }
+            let info = self.threads[child].as_ref().expect("child not exist");
+            // 主线程还没有在其他 hart 上切换出去时不能回收
+            if let (Status::Zombie, Some(_)) = (&info.status, &info.thread) {
+                if self.process(child).lock().threads.is_empty() {
+                    result = WaitResult::Exited(child, info.exit_code);
+                    children.remove(i);
+                    break;
+                }
+            }
+            if untraced {
+                let mut proc = self.process(child).lock();
+                if let (Some(sig), false) = (proc.signals.stopped, proc.signals.stop_reported) {
+                    proc.signals.stop_reported = true;
+                    result = WaitResult::Stopped(child, sig);
+                    break;
+                }
+            }
+            result = WaitResult::Running;
+        }
+        if let WaitResult::Exited(pid, _) = result {
+            {
+                let child = self.process(pid).lock();
+                parent.children_stats.add(&child.stats);
+                parent.children_stats.add(&child.children_stats);
+            }
+            self.threads[pid] = None;
+        }
+        result
+    }
+
+    /// Hand the process `pid` over to a new parent `parent`, and wake the new parent up.
+    pub fn reparent(&mut self, pid: Tid, parent: Tid) {
+        self.process(pid).lock().parent = Some(parent);
+        self.process(parent).lock().children.push(pid);
+        self.wakeup_process(parent);
+    }
+
+    /// The process whose pid is `pid`.
+    fn process(&self, pid: Tid) -> &Arc<Mutex<Process>> {
+        &self.threads[pid].as_ref().expect("process not exist").proc
+    }
+
+    /// The process whose pid is `pid`, `None` if there is no such process.
+    pub fn find_process(&self, pid: Tid) -> Option<Arc<Mutex<Process>>> {
+        let proc = &self.threads.get(pid)?.as_ref()?.proc;
+        if proc.lock().pid == pid {
+            Some(proc.clone())
+        } else {
+            None
+        }
+    }
+
+    /// The live processes in process group `pgid`.
+    pub fn process_group(&self, pgid: Tid) -> Vec<Tid> {
+        let mut pids = Vec::new();
+        for (tid, info) in self.threads.iter().enumerate() {
+            if let Some(info) = info {
+                let proc = info.proc.lock();
+                if proc.pid == tid && proc.pgid == pgid && !proc.threads.is_empty() {
+                    pids.push(tid);
+                }
+            }
+        }
+        pids
+    }
+
+    /// Wake up all the threads of process `pid`.
+    pub fn wakeup_process(&mut self, pid: Tid) {
+        let threads = self.process(pid).lock().threads.clone();
+        for tid in threads {
+            self.wakeup(tid);
+        }
+    }
+
+    /// Wake up the parent of process `pid` if it has one.
+    ///
+    /// The parent is read while holding the pool, so it cannot exit and be reaped meanwhile.
+    pub fn wakeup_parent(&mut self, pid: Tid) {
+        let parent = self.process(pid).lock().parent;
+        if let Some(parent) = parent {
+            self.wakeup_process(parent);
         }
     }
 
-    pub fn tick(&mut self) -> bool {
-        let ret = self.scheduler.tick();
-        ret
+    /// The handle to wake up the existing thread `tid` later.
+    pub fn handle(&self, tid: Tid) -> WakeupHandle {
+        let info = self.threads[tid].as_ref().expect("thread not exist");
+        WakeupHandle {
+            tid,
+            generation: info.generation,
+        }
     }
 
-    pub fn exit(&mut self, tid: Tid) {
-        self.threads[tid] = None;
-        self.scheduler.exit(tid);
+    /// Wake up the thread of `handle`, nothing happens if it has exited since.
+    pub fn wakeup_handle(&mut self, handle: WakeupHandle) {
+        let alive = match self.threads.get(handle.tid) {
+            Some(Some(info)) => info.generation == handle.generation,
+            _ => false,
+        };
+        if alive {
+            self.wakeup(handle.tid);
+        }
     }
 
+    /// Wake up thread `tid`, if it is not sleeping yet its next `set_sleep` fails instead.
+    ///
+    /// A late wakeup for an exited thread is ignored.
     pub fn wakeup(&mut self, tid: Tid) {
-        let proc = self.threads[tid]
-            .as_mut()
-            .expect("thread not exist when waking up");
-        proc.status = Status::Ready;
-        self.scheduler.push(tid);
+        let proc = match self.threads.get_mut(tid) {
+            Some(Some(info)) => info,
+            _ => return,
+        };
+        match proc.status {
+            Status::Sleeping => {
+                proc.status = Status::Ready;
+                proc.stats.wakeups += 1;
+                self.push(tid, true);
+            }
+            Status::Ready | Status::Running(_) => proc.wakeup_pending = true,
+            Status::Zombie => {}
+        }
     }
 
-    pub fn set_sleep(&mut self, tid: Tid) {
+    /// The running thread `tid` is going to sleep, it sleeps once it is switched out.
+    ///
+    /// Returns false if it has been woken up already, then it should not switch out.
+    pub fn set_sleep(&mut self, tid: Tid) -> bool {
         let proc = self.threads[tid].as_mut().expect("thread not exist");
-        proc.status = Status::Sleeping;
+        if proc.wakeup_pending {
+            proc.wakeup_pending = false;
+            return false;
+        }
+        proc.parking = true;
+        true
+    }
+
+    pub fn set_priority(&mut self, tid: Tid, priority: usize) {
+        let info = self.threads[tid].as_mut().expect("thread not exist");
+        info.priority = priority;
+        self.schedulers[info.hart].set_priority(tid, priority);
+    }
+
+    /// The nice value of thread `tid`, `None` if it does not exist.
+    pub fn get_nice(&self, tid: Tid) -> Option<isize> {
+        self.threads.get(tid)?.as_ref().map(|info| info.nice)
+    }
+
+    /// Set the nice value of thread `tid`, clamped to `NICE_MIN..=NICE_MAX`.
+    pub fn set_nice(&mut self, tid: Tid, nice: isize) -> bool {
+        let nice = nice.max(NICE_MIN).min(NICE_MAX);
+        match self.threads.get_mut(tid).and_then(|info| info.as_mut()) {
+            Some(info) => {
+                info.nice = nice;
+                self.schedulers[info.hart].set_nice(tid, nice);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Change the scheduling policy of the running thread `tid`, `None` for the normal class.
+    pub fn set_policy(&mut self, tid: Tid, policy: Option<Policy>) -> Result<(), SysError> {
+        let info = self.threads[tid].as_mut().expect("thread not exist");
+        let hart = info.hart;
+        match policy {
+            Some(policy) => {
+                let normal = !self.rt[hart].contains(tid);
+                self.rt[hart].set_policy(tid, policy)?;
+                // 离开普通调度器，回到普通类时重新加入
+                if normal {
+                    self.schedulers[hart].exit(tid);
+                }
+            }
+            None => info.stats.deadline_misses += self.rt[hart].exit(tid),
+        }
+        Ok(())
+    }
+
+    /// The statistics of thread `tid`, `None` if it does not exist.
+    pub fn stats(&self, tid: Tid) -> Option<ThreadStats> {
+        let info = self.threads.get(tid)?.as_ref()?;
+        let mut stats = info.stats;
+        stats.deadline_misses += self.rt[info.hart].deadline_misses(tid);
+        Some(stats)
+    }
+
+    /// The running thread `tid` gives up the CPU voluntarily.
+    ///
+    /// For a SCHED_DEADLINE thread it means the job of this period is done.
+    pub fn on_yield(&mut self, tid: Tid) {
+        let hart = self.threads[tid].as_ref().expect("thread not exist").hart;
+        self.rt[hart].job_done(tid);
     }
 }
@@ -1,24 +1,28 @@
 //! A naive timer
 
-use alloc::{boxed::Box, collections::BinaryHeap};
+use alloc::{boxed::Box, collections::BTreeMap, collections::BinaryHeap};
 use core::cmp::Ordering;
 
-use crate::timer::get_cycle;
-
 /// The type of callback function.
-type Callback = Box<dyn FnOnce() + Send + Sync + 'static>;
+type Callback = Box<dyn FnMut() + Send + Sync + 'static>;
+
+/// The handle of a timer event, to cancel it later.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct TimerId(u64);
 
-struct Node(u64, Callback);
+struct Event {
+    deadline: u64,
+    // 周期性的事件每次触发后推迟 period 再触发
+    period: Option<u64>,
+    callback: Callback,
+}
+
+// 按截止时间排序，同时到期的先加入的先触发
+struct Node(u64, TimerId);
 
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.0 < other.0 {
-            return Ordering::Greater;
-        } else if self.0 > other.0 {
-            return Ordering::Less;
-        } else {
-            return Ordering::Equal;
-        }
+        (other.0, other.1).cmp(&(self.0, self.1))
     }
 }
 
@@ -32,38 +36,113 @@ impl PartialOrd for Node {
 
 impl PartialEq for Node {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.0 == other.0 && self.1 == other.1
     }
 }
 
-/// A naive timer
+/// A naive timer, the deadlines are in timer cycles.
+///
+/// A cancelled or re-armed event leaves its old node in the heap, which is skipped when it pops.
 #[derive(Default)]
 pub struct Timer {
-    events: BinaryHeap<Node>,
+    queue: BinaryHeap<Node>,
+    events: BTreeMap<TimerId, Event>,
+    next_id: u64,
 }
 
 impl Timer {
     /// Add a timer with given `deadline`.
     ///
     /// The `callback` will be called on timer expired.
-    pub fn add(&mut self, deadline: u64, callback: impl FnOnce() + Send + Sync + 'static) {
-        self.events.push(Node(deadline, Box::new(callback)));
+    pub fn add(
+        &mut self,
+        deadline: u64,
+        callback: impl FnMut() + Send + Sync + 'static,
+    ) -> TimerId {
+        self.insert(deadline, None, Box::new(callback))
+    }
+
+    /// Add a timer which expires at `deadline` and then every `period`.
+    pub fn add_periodic(
+        &mut self,
+        deadline: u64,
+        period: u64,
+        callback: impl FnMut() + Send + Sync + 'static,
+    ) -> TimerId {
+        self.insert(deadline, Some(period.max(1)), Box::new(callback))
+    }
+
+    fn insert(&mut self, deadline: u64, period: Option<u64>, callback: Callback) -> TimerId {
+        self.next_id += 1;
+        let id = TimerId(self.next_id);
+        self.queue.push(Node(deadline, id));
+        self.events.insert(
+            id,
+            Event {
+                deadline,
+                period,
+                callback,
+            },
+        );
+        id
+    }
+
+    /// Cancel the timer `id`, returns when it would have expired next and its period.
+    ///
+    /// `None` if it has expired or been cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> Option<(u64, Option<u64>)> {
+        self.events
+            .remove(&id)
+            .map(|event| (event.deadline, event.period))
+    }
+
+    /// When the timer `id` expires next and its period, `None` if it is gone.
+    pub fn get(&self, id: TimerId) -> Option<(u64, Option<u64>)> {
+        self.events
+            .get(&id)
+            .map(|event| (event.deadline, event.period))
     }
 
     /// Called on each tick.
     ///
     /// The caller should give the current time `now`, and all expired timer will be trigger.
+    /// A periodic timer is triggered once even if several periods have passed.
     pub fn tick(&mut self, now: u64) {
-        while let Some(event) = self.events.peek() {
-            if event.0 > now {
+        while let Some(&Node(deadline, id)) = self.queue.peek() {
+            if deadline > now {
                 return;
             }
-            let callback = self.events.pop().unwrap().1;
-            callback();
+            self.queue.pop();
+            let event = match self.events.get_mut(&id) {
+                Some(event) if event.deadline == deadline => event,
+                // 已经取消或重新设置过的事件
+                _ => continue,
+            };
+            (event.callback)();
+            match event.period {
+                Some(period) => {
+                    while event.deadline <= now {
+                        event.deadline += period;
+                    }
+                    self.queue.push(Node(event.deadline, id));
+                }
+                None => {
+                    self.events.remove(&id);
+                }
+            }
         }
     }
-}
 
-pub fn now() -> u64 {
-    get_cycle() / crate::timer::TIMEBASE
+    /// The earliest deadline, if any.
+    pub fn next(&mut self) -> Option<u64> {
+        while let Some(&Node(deadline, id)) = self.queue.peek() {
+            match self.events.get(&id) {
+                Some(event) if event.deadline == deadline => return Some(deadline),
+                _ => {
+                    self.queue.pop();
+                }
+            }
+        }
+        None
+    }
 }
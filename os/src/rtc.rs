@@ -0,0 +1,20 @@
+//! The goldfish RTC of QEMU virt, which counts the nanoseconds since the Unix epoch.
+//!
+//! It is only read once at boot, the timer module keeps the time from then on.
+
+use crate::memory::access_pa_via_va;
+
+// 寄存器的物理地址，先读 TIME_LOW 时 TIME_HIGH 被锁存
+pub const RTC_BASE: usize = 0x0010_1000;
+const TIME_LOW: usize = 0x00;
+const TIME_HIGH: usize = 0x04;
+
+/// The nanoseconds since the Unix epoch
+pub fn read_ns() -> u64 {
+    let reg = |offset: usize| access_pa_via_va(RTC_BASE + offset) as *const u32;
+    unsafe {
+        let low = reg(TIME_LOW).read_volatile() as u64;
+        let high = reg(TIME_HIGH).read_volatile() as u64;
+        high << 32 | low
+    }
+}
@@ -0,0 +1,154 @@
+//! Bring up the other harts, and the inter-processor interrupts between them.
+//!
+//! The id of the current hart is kept in `tp`, which the trap entry restores when coming from user mode.
+
+use core::sync::atomic::{spin_loop_hint, AtomicBool, AtomicUsize, Ordering};
+
+use riscv::asm::sfence_vma_all;
+use spin::{Mutex, MutexGuard};
+
+use crate::consts::MAX_HARTS;
+use crate::sbi;
+
+// 0 号 hart 完成初始化后其他 hart 才开始运行
+static STARTED: AtomicBool = AtomicBool::new(false);
+// 已经开始调度线程的 hart
+static ONLINE: AtomicUsize = AtomicUsize::new(0);
+
+// IPI 的种类，可以同时有多个
+pub const IPI_RESCHED: usize = 1;
+pub const IPI_TLB_FLUSH: usize = 2;
+
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+static PENDING: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+
+// 每个 hart 正在使用的页表，即 satp 的值，TLB 击落只发给使用同一页表的 hart
+static SATP: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+// 每个 hart 被请求刷新 TLB 的次数，以及完成的刷新覆盖了前多少次请求
+static FLUSH_REQUESTED: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+static FLUSH_DONE: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+
+#[inline(always)]
+pub fn hart_id() -> usize {
+    let id: usize;
+    unsafe {
+        asm!("mv $0, tp" : "=r"(id) ::: "volatile");
+    }
+    id
+}
+
+/// Let the other harts go, called by the boot hart once the kernel is initialized.
+pub fn start_others() {
+    set_online();
+    STARTED.store(true, Ordering::Release);
+}
+
+/// The entry of the harts other than hart 0, they wait until hart 0 calls `start_others`.
+#[no_mangle]
+pub extern "C" fn others_main() -> ! {
+    while !STARTED.load(Ordering::Acquire) {
+        spin_loop_hint();
+    }
+    crate::memory::init_other();
+    crate::interrupt::init_other();
+    crate::process::init_other();
+    crate::timer::init_other();
+    set_online();
+    crate::process::run();
+    loop {}
+}
+
+fn set_online() {
+    ONLINE.fetch_or(1 << hart_id(), Ordering::SeqCst);
+}
+
+/// The harts that are scheduling threads, as a bit mask.
+pub fn online_mask() -> usize {
+    ONLINE.load(Ordering::SeqCst)
+}
+
+/// Send an IPI of `kind` to the harts in `hart_mask`.
+pub fn send_ipi(hart_mask: usize, kind: usize) {
+    for hart in 0..MAX_HARTS {
+        if hart_mask & (1 << hart) != 0 {
+            PENDING[hart].fetch_or(kind, Ordering::SeqCst);
+        }
+    }
+    sbi::send_ipi(hart_mask);
+}
+
+/// Record the page table `token` the current hart is switching to, before `satp` is written.
+pub fn set_satp(token: usize) {
+    SATP[hart_id()].store(token, Ordering::SeqCst);
+}
+
+/// Make the other harts running on the page table `token` flush their TLB, and wait until they have.
+///
+/// Called after a page table entry is changed, before the frame it pointed to is reused.
+/// A hart running the kernel flushes on its next interrupt, so the caller must not hold a lock
+/// the other harts may spin on, unless they take it with `lock_flushing`.
+pub fn tlb_shootdown(token: usize) {
+    let me = hart_id();
+    let mut targets = 0;
+    let mut tickets = [0; MAX_HARTS];
+    // 先修改页表项再登记请求，对方看到请求时刷新的一定是修改之后的页表项
+    for hart in 0..MAX_HARTS {
+        if hart != me
+            && online_mask() & (1 << hart) != 0
+            && SATP[hart].load(Ordering::SeqCst) == token
+        {
+            tickets[hart] = FLUSH_REQUESTED[hart].fetch_add(1, Ordering::SeqCst) + 1;
+            targets |= 1 << hart;
+        }
+    }
+    if targets == 0 {
+        return;
+    }
+    send_ipi(targets, IPI_TLB_FLUSH);
+    for hart in 0..MAX_HARTS {
+        while targets & (1 << hart) != 0 && FLUSH_DONE[hart].load(Ordering::SeqCst) < tickets[hart]
+        {
+            // 对方可能也在等待当前 hart 刷新
+            flush_tlb();
+            spin_loop_hint();
+        }
+    }
+}
+
+/// Lock `mutex`, flushing the TLB when asked to while spinning.
+///
+/// For the locks held by a hart waiting in `tlb_shootdown`, which would otherwise wait forever.
+pub fn lock_flushing<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    loop {
+        if let Some(guard) = mutex.try_lock() {
+            return guard;
+        }
+        flush_tlb();
+        spin_loop_hint();
+    }
+}
+
+// 完成发给当前 hart 的刷新请求
+fn flush_tlb() {
+    let hart = hart_id();
+    let requested = FLUSH_REQUESTED[hart].load(Ordering::SeqCst);
+    if FLUSH_DONE[hart].load(Ordering::SeqCst) < requested {
+        unsafe {
+            sfence_vma_all();
+        }
+        FLUSH_DONE[hart].store(requested, Ordering::SeqCst);
+    }
+}
+
+/// Handle the IPIs sent to the current hart, called on a supervisor software interrupt.
+pub fn handle_ipi() {
+    sbi::clear_ipi();
+    let pending = PENDING[hart_id()].swap(0, Ordering::SeqCst);
+    if pending & IPI_TLB_FLUSH != 0 {
+        flush_tlb();
+    }
+    // 空闲的 hart 从 wfi 返回后会自己取就绪的线程
+    if pending & IPI_RESCHED != 0 {
+        crate::process::preempt();
+    }
+}
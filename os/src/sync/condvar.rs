@@ -1,12 +1,13 @@
 use alloc::collections::VecDeque;
 
-use spin::Mutex;
+use spin::{Mutex, MutexGuard};
 
-use crate::process::{current_tid, park, wake_up, Tid};
+use crate::process::thread_pool::WakeupHandle;
+use crate::process::{current_handle, park, wake_up};
 
 #[derive(Default)]
 pub struct Condvar {
-    wait_queue: Mutex<VecDeque<Tid>>,
+    wait_queue: Mutex<VecDeque<WakeupHandle>>,
 }
 
 impl Condvar {
@@ -15,15 +16,42 @@ impl Condvar {
     }
 
     pub fn wait(&self) {
-        self.wait_queue.lock().push_back(current_tid());
+        self.wait_queue.lock().push_back(current_handle());
         park();
     }
 
+    /// Wait with the lock of the condition held, and release it only after queued.
+    ///
+    /// A notification after `guard` is released is not lost, even if it comes before `park`.
+    pub fn wait_unlock<T>(&self, guard: MutexGuard<T>) {
+        self.wait_queue.lock().push_back(current_handle());
+        drop(guard);
+        park();
+    }
+
+    /// Queue `handle` to be woken up by the next notification without waiting,
+    /// so that a thread can wait for several condvars at once.
+    pub fn register(&self, handle: WakeupHandle) {
+        self.wait_queue.lock().push_back(handle);
+    }
+
+    /// Remove `handle` queued by `register`, so that it does not take a later notification.
+    pub fn unregister(&self, handle: WakeupHandle) {
+        self.wait_queue.lock().retain(|&h| h != handle);
+    }
+
     pub fn notify(&self) {
-        let tid = self.wait_queue.lock().pop_front();
-        if let Some(tid) = tid {
-            wake_up(tid);
+        let handle = self.wait_queue.lock().pop_front();
+        if let Some(handle) = handle {
+            wake_up(handle);
         }
         /* yield_now(); */
     }
+
+    pub fn notify_all(&self) {
+        let queue: VecDeque<WakeupHandle> = core::mem::take(&mut *self.wait_queue.lock());
+        for handle in queue {
+            wake_up(handle);
+        }
+    }
 }
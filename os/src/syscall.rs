@@ -1,185 +1,843 @@
-use alloc::collections::VecDeque;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem::size_of;
 use core::ops::Add;
 
-use spin::{Mutex, MutexGuard};
+use rcore_fs::vfs::FsError;
+use spin::Mutex;
 
+use crate::consts::{ARG_MAX, NOFILE, PAGE_SIZE, USER_STR_MAX};
 use crate::context::TrapFrame;
-use crate::fs::file::FileDescriptorType;
+use crate::fs::file::{File, FileDescriptorType};
+use crate::fs::pipe::Pipe;
+use crate::fs::poll::{poll, PollFd};
+use crate::fs::stdio::STDIN;
+use crate::memory::user_access::{
+    check_user, copy_from_user, copy_to_user, get_user, put_user, strncpy_from_user,
+};
 use crate::process;
-use crate::process::sleep;
+use crate::process::realtime::{Policy, SCHED_DEADLINE, SCHED_FIFO, SCHED_OTHER, SCHED_RR};
+use crate::process::session;
+use crate::process::signal::{
+    self, SigAction, SigSet, NSIG, SIGKILL, SIGSTOP, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK,
+};
+use crate::timer::{self, get_cycle, CLOCK_FREQ};
 
+pub const SYS_IOCTL: usize = 29;
 pub const SYS_OPEN: usize = 56;
 pub const SYS_CLOSE: usize = 57;
 pub const SYS_PIPE: usize = 59;
+pub const SYS_PPOLL: usize = 73;
 pub const SYS_WRITE: usize = 64;
 pub const SYS_EXIT: usize = 93;
+pub const SYS_NANOSLEEP: usize = 101;
+pub const SYS_GETITIMER: usize = 102;
+pub const SYS_SETITIMER: usize = 103;
+pub const SYS_CLOCK_GETTIME: usize = 113;
 pub const SYS_READ: usize = 63;
+pub const SYS_SCHED_SETSCHEDULER: usize = 119;
+pub const SYS_YIELD: usize = 124;
+pub const SYS_KILL: usize = 129;
+pub const SYS_SIGACTION: usize = 134;
+pub const SYS_SIGPROCMASK: usize = 135;
+pub const SYS_SIGRETURN: usize = 139;
 pub const SYS_SETPRIORITY: usize = 140;
+pub const SYS_GETPRIORITY: usize = 141;
 pub const SYS_TIMES: usize = 153;
+pub const SYS_SETPGID: usize = 154;
+pub const SYS_GETPGID: usize = 155;
+pub const SYS_GETSID: usize = 156;
+pub const SYS_SETSID: usize = 157;
+pub const SYS_GETRUSAGE: usize = 165;
+pub const SYS_GETTIMEOFDAY: usize = 169;
+pub const SYS_GETPID: usize = 172;
+pub const SYS_GETPPID: usize = 173;
 pub const SYS_FORK: usize = 220;
 pub const SYS_EXEC: usize = 221;
+pub const SYS_WAIT: usize = 260;
+pub const SYS_CLONE: usize = 435;
+// uCore lab6 的 set_priority，优先级越大得到的 CPU 时间越多
+pub const SYS_LAB6_SET_PRIORITY: usize = 255;
+
+// getpriority 与 setpriority 的 which 参数，目前只支持按线程设置
+pub const PRIO_PROCESS: usize = 0;
+
+/// 系统调用的错误码，以相反数返回给用户程序，与 Linux 的 errno 一致
+#[repr(isize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SysError {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    EIO = 5,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    EMFILE = 24,
+    ENOTTY = 25,
+    ENOSPC = 28,
+    EPIPE = 32,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
+    ENOTEMPTY = 39,
+}
+
+pub type SysResult = Result<isize, SysError>;
+
+impl From<FsError> for SysError {
+    fn from(error: FsError) -> Self {
+        match error {
+            FsError::NotSupported => SysError::ENOSYS,
+            FsError::NotFile | FsError::IsDir => SysError::EISDIR,
+            FsError::NotDir => SysError::ENOTDIR,
+            FsError::EntryNotFound => SysError::ENOENT,
+            FsError::EntryExist => SysError::EEXIST,
+            FsError::InvalidParam => SysError::EINVAL,
+            FsError::NoDeviceSpace => SysError::ENOSPC,
+            FsError::DirNotEmpty => SysError::ENOTEMPTY,
+            _ => SysError::EIO,
+        }
+    }
+}
 
 pub fn syscall(id: usize, args: [usize; 3], tf: &mut TrapFrame) -> isize {
+    match do_syscall(id, args, tf) {
+        Ok(ret) => ret,
+        Err(error) => -(error as isize),
+    }
+}
+
+fn do_syscall(id: usize, args: [usize; 3], tf: &mut TrapFrame) -> SysResult {
     match id {
+        SYS_IOCTL => sys_ioctl(args[0], args[1], args[2]),
         SYS_OPEN => sys_open(args[0] as *const u8, args[1] as i32),
         SYS_CLOSE => sys_close(args[0] as i32),
-        SYS_READ => unsafe { sys_read(args[0], args[1] as *mut u8, args[2]) },
-        SYS_WRITE => unsafe { sys_write(args[0], args[1] as *const u8, args[2]) },
+        SYS_READ => sys_read(args[0], args[1] as *mut u8, args[2]),
+        SYS_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
         SYS_EXIT => {
             sys_exit(args[0]);
-            0
+            Ok(0)
+        }
+        SYS_NANOSLEEP => sys_nanosleep(args[0] as *const TimeSpec, args[1] as *mut TimeSpec),
+        SYS_GETITIMER => sys_getitimer(args[0], args[1] as *mut ItimerVal),
+        SYS_SETITIMER => sys_setitimer(
+            args[0],
+            args[1] as *const ItimerVal,
+            args[2] as *mut ItimerVal,
+        ),
+        SYS_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut TimeSpec),
+        SYS_SCHED_SETSCHEDULER => {
+            sys_sched_setscheduler(args[0], args[1], args[2] as *const SchedParam)
+        }
+        SYS_YIELD => {
+            process::sched_yield();
+            Ok(0)
+        }
+        SYS_KILL => sys_kill(args[0] as isize, args[1]),
+        SYS_SIGACTION => sys_sigaction(
+            args[0],
+            args[1] as *const SigAction,
+            args[2] as *mut SigAction,
+        ),
+        SYS_SIGPROCMASK => {
+            sys_sigprocmask(args[0], args[1] as *const SigSet, args[2] as *mut SigSet)
+        }
+        SYS_SIGRETURN => signal::sigreturn(tf),
+        SYS_LAB6_SET_PRIORITY => {
+            process::set_priority(args[0]);
+            Ok(0)
         }
-        SYS_SETPRIORITY => {
-            let p = &mut (*(*process::CPU.inner().pool).scheduler).threads[process::current_tid()];
-            p.pass = 65536 / args[0];
-            0
+        SYS_SETPRIORITY => sys_setpriority(args[0], args[1], args[2] as isize),
+        SYS_GETPRIORITY => sys_getpriority(args[0], args[1]),
+        SYS_TIMES => sys_times(args[0] as *mut Tms),
+        SYS_SETPGID => {
+            session::set_pgid(args[0], args[1])?;
+            Ok(0)
         }
-        SYS_TIMES => crate::timer::get_cycle() as isize / 200000,
+        SYS_GETPGID => Ok(session::get_pgid(args[0])? as isize),
+        SYS_GETSID => Ok(session::get_sid(args[0])? as isize),
+        SYS_SETSID => Ok(session::set_sid()? as isize),
+        SYS_GETRUSAGE => sys_getrusage(args[0] as isize, args[1] as *mut Rusage),
+        SYS_GETTIMEOFDAY => sys_gettimeofday(args[0] as *mut TimeVal),
+        SYS_GETPID => Ok(process::current_pid() as isize),
+        SYS_GETPPID => Ok(process::current_process().lock().parent.unwrap_or(0) as isize),
         SYS_FORK => sys_fork(tf),
-        SYS_EXEC => sys_exec(args[0] as *const u8),
-        SYS_PIPE => unsafe { sys_pipe(args[0] as *mut i32) },
+        SYS_CLONE => sys_clone(args[0], args[1], args[2]),
+        SYS_EXEC => sys_exec(
+            args[0] as *const u8,
+            args[1] as *const *const u8,
+            args[2] as *const *const u8,
+            tf,
+        ),
+        SYS_WAIT => sys_wait(args[0], args[1] as *mut i32, args[2]),
+        SYS_PIPE => sys_pipe(args[0] as *mut [i32; 2]),
+        SYS_PPOLL => sys_ppoll(args[0] as *mut PollFd, args[1], args[2] as *const TimeSpec),
         _ => {
-            panic!("unknown syscall id {}", id);
+            println!("unknown syscall id {}", id);
+            Err(SysError::ENOSYS)
         }
     }
 }
 
-fn sys_open(path: *const u8, flags: i32) -> isize {
-    let thread = process::current_thread_mut();
-    let fd = thread.alloc_fd() as isize;
-    thread.ofile[fd as usize]
+fn sys_open(path: *const u8, flags: i32) -> SysResult {
+    let path = strncpy_from_user(path, USER_STR_MAX)?;
+    let mut proc = process::current_process().lock();
+    let fd = proc.alloc_fd().ok_or(SysError::EMFILE)?;
+    let result = proc.ofile[fd as usize]
         .as_ref()
         .unwrap()
         .lock()
-        .open_file(unsafe { from_cstr(path) }, flags);
-    fd
+        .open_file(&path, flags);
+    if let Err(error) = result {
+        proc.dealloc_fd(fd);
+        return Err(error.into());
+    }
+    Ok(fd as isize)
 }
 
-unsafe fn sys_pipe(pipefd: *mut i32) -> isize {
-    let thread = process::current_thread_mut();
-    let fd1 = thread.alloc_fd() as isize;
-    let fd2 = thread.alloc_fd() as isize;
-    let pipe: Arc<Mutex<VecDeque<u8>>> = Default::default();
-    thread.ofile[fd1 as usize]
+fn sys_pipe(pipefd: *mut [i32; 2]) -> SysResult {
+    check_user(pipefd as usize, size_of::<[i32; 2]>(), true)?;
+    let mut proc = process::current_process().lock();
+    let fd1 = proc.alloc_fd().ok_or(SysError::EMFILE)?;
+    let fd2 = match proc.alloc_fd() {
+        Some(fd) => fd,
+        None => {
+            proc.dealloc_fd(fd1);
+            return Err(SysError::EMFILE);
+        }
+    };
+    let (read_end, write_end) = Pipe::new();
+    proc.ofile[fd1 as usize]
         .as_ref()
         .unwrap()
         .lock()
-        .open_pipe(pipe.clone());
-    thread.ofile[fd2 as usize]
+        .open_pipe(read_end);
+    proc.ofile[fd2 as usize]
         .as_ref()
         .unwrap()
         .lock()
-        .open_pipe(pipe);
-    *pipefd.add(0) = fd1 as i32;
-    *pipefd.add(1) = fd2 as i32;
-    0
+        .open_pipe(write_end);
+    drop(proc);
+    put_user(pipefd, [fd1, fd2])?;
+    Ok(0)
 }
 
-fn sys_close(fd: i32) -> isize {
-    let thread = process::current_thread_mut();
-    assert!(thread.ofile[fd as usize].is_some());
-    thread.dealloc_fd(fd);
-    0
+fn sys_close(fd: i32) -> SysResult {
+    let file = {
+        let mut proc = process::current_process().lock();
+        if fd < 0 || fd as usize >= NOFILE || proc.ofile[fd as usize].is_none() {
+            return Err(SysError::EBADF);
+        }
+        proc.dealloc_fd(fd)
+    };
+    // 关闭管道会唤醒等待它的线程，不能持有进程的锁
+    drop(file);
+    Ok(0)
+}
+
+// 取出当前进程中打开的文件
+fn get_file(fd: usize) -> Result<Arc<Mutex<File>>, SysError> {
+    if fd >= NOFILE {
+        return Err(SysError::EBADF);
+    }
+    process::current_process().lock().ofile[fd]
+        .clone()
+        .ok_or(SysError::EBADF)
+}
+
+// ioctl 的 request，只有控制台支持
+pub const TIOCSCTTY: usize = 0x540e;
+pub const TIOCGPGRP: usize = 0x540f;
+pub const TIOCSPGRP: usize = 0x5410;
+
+// 控制台的前台进程组以 i32 存放在 arg 指向的位置
+fn sys_ioctl(fd: usize, request: usize, arg: usize) -> SysResult {
+    match get_file(fd)?.lock().get_fdtype() {
+        FileDescriptorType::FdStdin | FileDescriptorType::FdStdout => {}
+        _ => return Err(SysError::ENOTTY),
+    }
+    match request {
+        TIOCSCTTY => STDIN.set_ctty()?,
+        TIOCGPGRP => put_user(arg as *mut i32, STDIN.get_pgrp()? as i32)?,
+        TIOCSPGRP => {
+            let pgid = get_user(arg as *const i32)?;
+            if pgid < 0 {
+                return Err(SysError::EINVAL);
+            }
+            STDIN.set_pgrp(pgid as usize)?;
+        }
+        _ => return Err(SysError::EINVAL),
+    }
+    Ok(0)
 }
 
 fn sys_exit(code: usize) {
     process::exit(code);
 }
 
-unsafe fn sys_read(fd: usize, base: *mut u8, len: usize) -> isize {
-    if fd == 0 {
-        // 如果是标准输入
-        *base = crate::fs::stdio::STDIN.pop() as u8;
-        return 1;
-    } else {
-        let thread = process::current_thread_mut();
-        assert!(thread.ofile[fd].is_some());
-        let mut file = thread.ofile[fd as usize].as_ref().unwrap().lock();
-        assert!(file.get_readable());
-        match file.get_fdtype() {
-            FileDescriptorType::FdInode => {
-                let mut offset = file.get_offset();
-                let s = file
-                    .inode
-                    .clone()
-                    .unwrap()
-                    .read_at(offset, core::slice::from_raw_parts_mut(base, len))
-                    .unwrap();
-                offset += s;
-                file.set_offset(offset);
-                return s as isize;
+fn sys_read(fd: usize, base: *mut u8, len: usize) -> SysResult {
+    check_user(base as usize, len, true)?;
+    let file = get_file(fd)?;
+    if !file.lock().get_readable() {
+        return Err(SysError::EBADF);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let fdtype = file.lock().get_fdtype();
+    match fdtype {
+        FileDescriptorType::FdStdin => {
+            // 阻塞时不能持有文件的锁，一次最多读一页
+            let mut buf = [0u8; PAGE_SIZE];
+            let n = STDIN.read(&mut buf[..len.min(PAGE_SIZE)])?;
+            copy_to_user(base, &buf[..n])?;
+            Ok(n as isize)
+        }
+        FileDescriptorType::FdInode => {
+            let mut file = file.lock();
+            let mut offset = file.get_offset();
+            let mut buf = alloc::vec![0u8; len];
+            let s = file.inode.clone().unwrap().read_at(offset, &mut buf)?;
+            copy_to_user(base, &buf[..s])?;
+            offset += s;
+            file.set_offset(offset);
+            Ok(s as isize)
+        }
+        FileDescriptorType::FdPipe => {
+            // 阻塞时不能持有文件的锁，一次最多读一页
+            let pipe = file.lock().pipe.as_ref().unwrap().pipe.clone();
+            let mut buf = [0u8; PAGE_SIZE];
+            let n = pipe.read(&mut buf[..len.min(PAGE_SIZE)])?;
+            copy_to_user(base, &buf[..n])?;
+            Ok(n as isize)
+        }
+        _ => Err(SysError::EBADF),
+    }
+}
+
+fn sys_write(fd: usize, base: *const u8, len: usize) -> SysResult {
+    check_user(base as usize, len, false)?;
+    let file = get_file(fd)?;
+    if !file.lock().get_writable() {
+        return Err(SysError::EBADF);
+    }
+    let fdtype = file.lock().get_fdtype();
+    match fdtype {
+        FileDescriptorType::FdStdout => {
+            // 按页复制到内核中再输出
+            let mut buf = [0u8; PAGE_SIZE];
+            let mut written = 0;
+            while written < len {
+                let n = (len - written).min(PAGE_SIZE);
+                copy_from_user(&mut buf[..n], base.wrapping_add(written))?;
+                crate::io::put_bytes(&buf[..n]);
+                written += n;
             }
-            FileDescriptorType::FdPipe => {
-                loop {
-                    let mut lock: MutexGuard<VecDeque<u8>> = file.pipe.as_ref().unwrap().lock();
-                    if let Some(c) = lock.pop_back() {
-                        *base = c;
-                        return 1;
-                    } else {
-                        drop(lock);
-                        sleep(1);
-                    }
+            Ok(len as isize)
+        }
+        FileDescriptorType::FdInode => {
+            let mut file = file.lock();
+            let mut offset = file.get_offset();
+            let mut buf = alloc::vec![0u8; len];
+            copy_from_user(&mut buf, base)?;
+            let s = file.inode.clone().unwrap().write_at(offset, &buf)?;
+            offset += s;
+            file.set_offset(offset);
+            Ok(s as isize)
+        }
+        FileDescriptorType::FdPipe => {
+            let pipe = file.lock().pipe.as_ref().unwrap().pipe.clone();
+            let mut buf = [0u8; PAGE_SIZE];
+            let mut written = 0;
+            while written < len {
+                let n = (len - written).min(PAGE_SIZE);
+                copy_from_user(&mut buf[..n], base.wrapping_add(written))?;
+                match pipe.write(&buf[..n]) {
+                    Ok(s) if s == n => written += n,
+                    // 读端在写入途中全部关闭
+                    Ok(s) => return Ok((written + s) as isize),
+                    Err(error) if written == 0 => return Err(error),
+                    Err(_) => break,
                 }
             }
-            _ => {
-                panic!("fdtype not handled!");
-            }
+            Ok(written as isize)
+        }
+        _ => Err(SysError::EBADF),
+    }
+}
+
+// 读取以空指针结尾的字符串数组，数组指针为空时视为空数组
+// 每读一项从 room 中减去它在用户栈上占用的大小，不够时返回 E2BIG
+fn from_cstr_array(mut array: *const *const u8, room: &mut usize) -> Result<Vec<String>, SysError> {
+    let mut strs = Vec::new();
+    if array.is_null() {
+        return Ok(strs);
+    }
+    loop {
+        let s = get_user(array)?;
+        if s.is_null() {
+            return Ok(strs);
         }
+        let s = strncpy_from_user(s, USER_STR_MAX)?;
+        *room = room
+            .checked_sub(size_of::<usize>() + s.len() + 1)
+            .ok_or(SysError::E2BIG)?;
+        strs.push(s);
+        array = array.wrapping_add(1);
     }
 }
 
-unsafe fn sys_write(fd: usize, base: *const u8, len: usize) -> isize {
-    if fd == 1 {
-        assert!(len == 1);
-        crate::io::putchar(*base as char);
-        return 1;
+// 子进程的返回值在 Context::new_fork 中被置为 0
+fn sys_fork(tf: &mut TrapFrame) -> SysResult {
+    let new_thread = process::current_thread_mut().fork(tf);
+    let pid = process::add_thread(new_thread);
+    process::current_process().lock().children.push(pid);
+    // 子进程继承 nice 值
+    if let Some(nice) = process::get_nice(process::current_tid()) {
+        process::set_nice(pid, nice);
+    }
+    Ok(pid as isize)
+}
+
+// who 为 0 时表示当前线程
+fn prio_target(which: usize, who: usize) -> Result<usize, SysError> {
+    if which != PRIO_PROCESS {
+        return Err(SysError::EINVAL);
+    }
+    match who {
+        0 => Ok(process::current_tid()),
+        tid => Ok(tid),
+    }
+}
+
+fn sys_setpriority(which: usize, who: usize, nice: isize) -> SysResult {
+    let tid = prio_target(which, who)?;
+    if process::set_nice(tid, nice) {
+        Ok(0)
     } else {
-        let thread = process::current_thread_mut();
-        assert!(thread.ofile[fd].is_some());
-        let mut file = thread.ofile[fd as usize].as_ref().unwrap().lock();
-        assert!(file.get_writable());
-        match file.get_fdtype() {
-            FileDescriptorType::FdInode => {
-                let mut offset = file.get_offset();
-                let s = file
-                    .inode
-                    .clone()
-                    .unwrap()
-                    .write_at(offset, core::slice::from_raw_parts(base, len))
-                    .unwrap();
-                offset += s;
-                file.set_offset(offset);
-                return s as isize;
-            }
-            FileDescriptorType::FdPipe => {
-                let mut lock: MutexGuard<VecDeque<u8>> = file.pipe.as_ref().unwrap().lock();
-                lock.push_front(*base);
-                return 1;
-            }
-            _ => {
-                panic!("fdtype not handled!");
-            }
+        Err(SysError::ESRCH)
+    }
+}
+
+// 与 Linux 相同返回 20 - nice，避免与错误码混淆
+fn sys_getpriority(which: usize, who: usize) -> SysResult {
+    let tid = prio_target(which, who)?;
+    let nice = process::get_nice(tid).ok_or(SysError::ESRCH)?;
+    Ok(20 - nice)
+}
+
+// pid 为 0 时发往当前进程组，小于 -1 时发往进程组 -pid，不支持发往所有进程
+fn sys_kill(pid: isize, sig: usize) -> SysResult {
+    match pid {
+        0 => signal::send_group(session::get_pgid(0)?, sig)?,
+        -1 => return Err(SysError::EINVAL),
+        pid if pid < 0 => signal::send_group(-pid as usize, sig)?,
+        pid => signal::send(pid as usize, sig)?,
+    }
+    Ok(0)
+}
+
+fn sys_sigaction(sig: usize, act: *const SigAction, oldact: *mut SigAction) -> SysResult {
+    if sig == 0 || sig >= NSIG {
+        return Err(SysError::EINVAL);
+    }
+    // 在持有进程的锁之前读取用户内存
+    let act = if act.is_null() {
+        None
+    } else {
+        Some(get_user(act)?)
+    };
+    if act.is_some() && (sig == SIGKILL || sig == SIGSTOP) {
+        return Err(SysError::EINVAL);
+    }
+    let old = {
+        let mut proc = process::current_process().lock();
+        let old = proc.signals.actions[sig];
+        if let Some(act) = act {
+            proc.signals.actions[sig] = act;
         }
+        old
+    };
+    if !oldact.is_null() {
+        put_user(oldact, old)?;
     }
+    Ok(0)
 }
 
-pub unsafe fn from_cstr(s: *const u8) -> &'static str {
-    use core::{slice, str};
-    let len = (0usize..).find(|&i| *s.add(i) == 0).unwrap();
-    str::from_utf8(slice::from_raw_parts(s, len)).unwrap()
+fn sys_sigprocmask(how: usize, set: *const SigSet, oldset: *mut SigSet) -> SysResult {
+    let set = if set.is_null() {
+        None
+    } else {
+        Some(get_user(set)?)
+    };
+    let old = {
+        let mut proc = process::current_process().lock();
+        let old = proc.signals.blocked;
+        if let Some(set) = set {
+            let blocked = match how {
+                SIG_BLOCK => old | set,
+                SIG_UNBLOCK => old & !set,
+                SIG_SETMASK => set,
+                _ => return Err(SysError::EINVAL),
+            };
+            proc.signals.set_blocked(blocked);
+        }
+        old
+    };
+    if !oldset.is_null() {
+        put_user(oldset, old)?;
+    }
+    Ok(0)
 }
 
-fn sys_fork(tf: &mut TrapFrame) -> isize {
-    let new_thread = process::current_thread_mut().fork(tf);
-    let tid = process::add_thread(new_thread);
-    tid as isize
+// times 的时间单位，每秒 50 个 tick
+const CYCLES_PER_TICK: u64 = 200000;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Tms {
+    pub tms_utime: isize,
+    pub tms_stime: isize,
+    pub tms_cutime: isize,
+    pub tms_cstime: isize,
+}
+
+// 返回开机以来的 tick 数，buf 不为空时填入当前进程与已回收的子进程使用的 CPU 时间
+fn sys_times(buf: *mut Tms) -> SysResult {
+    if !buf.is_null() {
+        let (stats, children) = process::process_stats();
+        let ticks = |cycles: u64| (cycles / CYCLES_PER_TICK) as isize;
+        let tms = Tms {
+            tms_utime: ticks(stats.usage.utime),
+            tms_stime: ticks(stats.usage.stime),
+            tms_cutime: ticks(children.usage.utime),
+            tms_cstime: ticks(children.usage.stime),
+        };
+        put_user(buf, tms)?;
+    }
+    Ok((get_cycle() / CYCLES_PER_TICK) as isize)
+}
+
+// getrusage 的 who 参数
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+pub const RUSAGE_THREAD: isize = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    fn from_ns(ns: u64) -> Self {
+        TimeVal {
+            sec: (ns / 1_000_000_000) as usize,
+            usec: (ns % 1_000_000_000 / 1_000) as usize,
+        }
+    }
+
+    fn from_cycles(cycles: u64) -> Self {
+        TimeVal {
+            sec: (cycles / CLOCK_FREQ) as usize,
+            usec: (cycles % CLOCK_FREQ * 1_000_000 / CLOCK_FREQ) as usize,
+        }
+    }
+
+    // 不足一个周期的部分向上取整
+    fn to_cycles(&self) -> u64 {
+        let usec = (self.usec as u64 * CLOCK_FREQ + 999_999) / 1_000_000;
+        (self.sec as u64)
+            .saturating_mul(CLOCK_FREQ)
+            .saturating_add(usec)
+    }
+}
+
+// setitimer 的 which 参数，只支持按实际时间计时
+pub const ITIMER_REAL: usize = 0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct ItimerVal {
+    pub interval: TimeVal,
+    pub value: TimeVal,
+}
+
+impl ItimerVal {
+    fn from_cycles((value, interval): (u64, u64)) -> Self {
+        let mut itimer = ItimerVal {
+            interval: TimeVal::from_cycles(interval),
+            value: TimeVal::from_cycles(value),
+        };
+        // 与 Linux 一致，没有到期的定时器剩余时间至少为 1 微秒
+        if value != 0 && itimer.value.sec == 0 && itimer.value.usec == 0 {
+            itimer.value.usec = 1;
+        }
+        itimer
+    }
 }
 
-fn sys_exec(path: *const u8) -> isize {
-    let valid = process::execute(unsafe { from_cstr(path) }, Some(process::current_tid()));
-    if valid {
-        process::park();
+fn sys_getitimer(which: usize, curr: *mut ItimerVal) -> SysResult {
+    if which != ITIMER_REAL {
+        return Err(SysError::EINVAL);
+    }
+    put_user(curr, ItimerVal::from_cycles(process::get_real_timer()))?;
+    Ok(0)
+}
+
+// 到期时发送 SIGALRM，interval 不为 0 时此后每隔 interval 发送一次
+fn sys_setitimer(which: usize, new: *const ItimerVal, old: *mut ItimerVal) -> SysResult {
+    if which != ITIMER_REAL {
+        return Err(SysError::EINVAL);
+    }
+    let new = get_user(new)?;
+    if new.value.usec >= 1_000_000 || new.interval.usec >= 1_000_000 {
+        return Err(SysError::EINVAL);
+    }
+    if !old.is_null() {
+        check_user(old as usize, size_of::<ItimerVal>(), true)?;
+    }
+    let prev = process::set_real_timer(new.value.to_cycles(), new.interval.to_cycles());
+    if !old.is_null() {
+        put_user(old, ItimerVal::from_cycles(prev))?;
+    }
+    Ok(0)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    fn from_ns(ns: u64) -> Self {
+        TimeSpec {
+            sec: (ns / 1_000_000_000) as usize,
+            nsec: (ns % 1_000_000_000) as usize,
+        }
+    }
+
+    fn from_cycles(cycles: u64) -> Self {
+        TimeSpec {
+            sec: (cycles / CLOCK_FREQ) as usize,
+            nsec: (cycles % CLOCK_FREQ * 1_000_000_000 / CLOCK_FREQ) as usize,
+        }
+    }
+
+    // 不足一个周期的部分向上取整，睡眠时间不会比要求的短
+    fn to_cycles(&self) -> u64 {
+        let nsec = (self.nsec as u64 * CLOCK_FREQ + 999_999_999) / 1_000_000_000;
+        (self.sec as u64)
+            .saturating_mul(CLOCK_FREQ)
+            .saturating_add(nsec)
+    }
+}
+
+// clock_gettime 的时钟，CLOCK_MONOTONIC 从开机时开始计时
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+fn sys_clock_gettime(clock: usize, tp: *mut TimeSpec) -> SysResult {
+    let ns = match clock {
+        CLOCK_REALTIME => timer::realtime_ns(),
+        CLOCK_MONOTONIC => timer::monotonic_ns(),
+        _ => return Err(SysError::EINVAL),
+    };
+    put_user(tp, TimeSpec::from_ns(ns))?;
+    Ok(0)
+}
+
+// 不支持时区，第二个参数被忽略
+fn sys_gettimeofday(tv: *mut TimeVal) -> SysResult {
+    put_user(tv, TimeVal::from_ns(timer::realtime_ns()))?;
+    Ok(0)
+}
+
+// 被信号打断时返回 EINTR，rem 不为空时填入剩余的时间
+fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> SysResult {
+    let req = get_user(req)?;
+    if req.nsec >= 1_000_000_000 {
+        return Err(SysError::EINVAL);
+    }
+    let deadline = get_cycle().saturating_add(req.to_cycles());
+    if let Err(error) = process::sleep_until(deadline) {
+        if !rem.is_null() {
+            let left = deadline.saturating_sub(get_cycle());
+            put_user(rem, TimeSpec::from_cycles(left))?;
+        }
+        return Err(error);
+    }
+    Ok(0)
+}
+
+// timeout 为空时一直等待，不支持 sigmask 参数
+fn sys_ppoll(fds: *mut PollFd, nfds: usize, timeout: *const TimeSpec) -> SysResult {
+    if nfds > NOFILE {
+        return Err(SysError::EINVAL);
+    }
+    check_user(fds as usize, nfds * size_of::<PollFd>(), true)?;
+    let deadline = if timeout.is_null() {
+        None
+    } else {
+        let timeout = get_user(timeout)?;
+        if timeout.nsec >= 1_000_000_000 {
+            return Err(SysError::EINVAL);
+        }
+        Some(get_cycle().saturating_add(timeout.to_cycles()))
+    };
+    let mut pfds = Vec::with_capacity(nfds);
+    for i in 0..nfds {
+        pfds.push(get_user(fds.wrapping_add(i))?);
+    }
+    let ready = poll(&mut pfds, deadline)?;
+    for (i, pfd) in pfds.into_iter().enumerate() {
+        put_user(fds.wrapping_add(i), pfd)?;
+    }
+    Ok(ready as isize)
+}
+
+/// The resource usage returned by getrusage.
+///
+/// Unlike Linux, only the fields counted by the kernel are here, and the wakeups are added.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    pub ru_nvcsw: usize,
+    pub ru_nivcsw: usize,
+    pub ru_nwakeups: usize,
+}
+
+fn sys_getrusage(who: isize, buf: *mut Rusage) -> SysResult {
+    let stats = match who {
+        RUSAGE_SELF => process::process_stats().0,
+        RUSAGE_CHILDREN => process::process_stats().1,
+        RUSAGE_THREAD => process::current_stats(),
+        _ => return Err(SysError::EINVAL),
+    };
+    let usage = stats.usage;
+    let rusage = Rusage {
+        ru_utime: TimeVal::from_cycles(usage.utime),
+        ru_stime: TimeVal::from_cycles(usage.stime),
+        ru_nvcsw: usage.nvcsw,
+        ru_nivcsw: usage.nivcsw,
+        ru_nwakeups: stats.wakeups,
+    };
+    put_user(buf, rusage)?;
+    Ok(0)
+}
+
+/// The parameters of sched_setscheduler, times are in microseconds.
+///
+/// Unlike Linux, the runtime, deadline and period of SCHED_DEADLINE are also given here.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SchedParam {
+    pub priority: usize,
+    pub runtime: usize,
+    pub deadline: usize,
+    pub period: usize,
+}
+
+// 只能设置当前线程的调度策略，pid 为 0 或当前线程的 tid
+fn sys_sched_setscheduler(pid: usize, policy: usize, param: *const SchedParam) -> SysResult {
+    if pid != 0 && pid != process::current_tid() {
+        return Err(SysError::EPERM);
+    }
+    let cycles = |us: usize| {
+        if us > u32::max_value() as usize {
+            return Err(SysError::EINVAL);
+        }
+        Ok(us as u64 * CLOCK_FREQ / 1_000_000)
+    };
+    // SCHED_OTHER 不需要 param，可以为空
+    let policy = match policy {
+        SCHED_OTHER => None,
+        SCHED_FIFO => Some(Policy::Fifo(get_user(param)?.priority)),
+        SCHED_RR => Some(Policy::RoundRobin(get_user(param)?.priority)),
+        SCHED_DEADLINE => {
+            let param = get_user(param)?;
+            Some(Policy::Deadline {
+                runtime: cycles(param.runtime)?,
+                deadline: cycles(param.deadline)?,
+                period: cycles(param.period)?,
+            })
+        }
+        _ => return Err(SysError::EINVAL),
+    };
+    process::set_policy(policy)?;
+    Ok(0)
+}
+
+// 在当前进程中创建新线程，与其共享地址空间和文件描述符
+fn sys_clone(entry: usize, ustack_top: usize, arg: usize) -> SysResult {
+    let new_thread = process::current_thread_mut().new_clone(entry, ustack_top, arg);
+    Ok(process::add_thread(new_thread) as isize)
+}
+
+// 以 argv 与 envp 为参数在当前进程中运行新程序
+// 成功时不返回原程序，返回值 argc 作为新程序的 a0
+fn sys_exec(
+    path: *const u8,
+    argv: *const *const u8,
+    envp: *const *const u8,
+    tf: &mut TrapFrame,
+) -> SysResult {
+    // 参数在旧的地址空间中，要在替换之前拷贝出来
+    let path = strncpy_from_user(path, USER_STR_MAX)?;
+    let mut room = ARG_MAX;
+    let args = from_cstr_array(argv, &mut room)?;
+    let envs = from_cstr_array(envp, &mut room)?;
+    let argc = args.len() as isize;
+    process::exec(&path, args, envs, tf)?;
+    Ok(argc)
+}
+
+// wait 的 options，WNOHANG 不阻塞，WUNTRACED 在子进程停止时也返回
+pub const WNOHANG: usize = 1;
+pub const WUNTRACED: usize = 2;
+
+// pid 为 0 时等待任意一个子进程，返回被回收或停止的子进程的 pid
+// WNOHANG 时子进程都还在运行返回 0
+fn sys_wait(pid: usize, code: *mut i32, options: usize) -> SysResult {
+    if options & !(WNOHANG | WUNTRACED) != 0 {
+        return Err(SysError::EINVAL);
+    }
+    // 回收子进程之前检查，以免退出码丢失
+    if !code.is_null() {
+        check_user(code as usize, size_of::<i32>(), true)?;
+    }
+    let untraced = options & WUNTRACED != 0;
+    let result = if options & WNOHANG != 0 {
+        match process::try_wait(pid, untraced) {
+            Err(SysError::EAGAIN) => return Ok(0),
+            result => result?,
+        }
+    } else {
+        process::wait(pid, untraced)
+    };
+    match result {
+        Some((pid, exit_code)) => {
+            if !code.is_null() {
+                put_user(code, exit_code as i32)?;
+            }
+            Ok(pid as isize)
+        }
+        None => Err(SysError::ECHILD),
     }
-    return 0;
 }
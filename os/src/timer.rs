@@ -1,23 +1,84 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
 use riscv::register::{sie, time};
 
+use crate::consts::MAX_HARTS;
+use crate::rtc;
 use crate::sbi::set_timer;
+use crate::smp::hart_id;
 
 pub static mut TICKS: usize = 0;
 
+// 时间片的长度
 pub(crate) static TIMEBASE: u64 = 100000;
+// QEMU virt 上 time 寄存器的频率
+pub const CLOCK_FREQ: u64 = 10_000_000;
+
+// 每个 hart 的时钟中断下一次触发的时间，u64::MAX 表示不触发
+const NEVER: AtomicU64 = AtomicU64::new(u64::MAX);
+static NEXT_EVENT: [AtomicU64; MAX_HARTS] = [NEVER; MAX_HARTS];
+
+// time 寄存器为 0 时距 Unix 纪元的纳秒数
+static BOOT_TIME_NS: AtomicU64 = AtomicU64::new(0);
+
 pub fn init() {
     unsafe {
         TICKS = 0;
         sie::set_stimer();
     }
+    BOOT_TIME_NS.store(
+        rtc::read_ns().saturating_sub(monotonic_ns()),
+        Ordering::Relaxed,
+    );
     clock_set_next_event();
     println!("++++ setup timer!     ++++");
 }
 
+pub fn init_other() {
+    unsafe {
+        sie::set_stimer();
+    }
+    clock_set_next_event();
+}
+
+/// Fire the next timer interrupt of the current hart at the end of the time slice starting now.
 pub fn clock_set_next_event() {
-    set_timer(get_cycle() + TIMEBASE);
+    set_next_event(Some(next_slice()));
+}
+
+/// The end of a time slice starting now
+pub fn next_slice() -> u64 {
+    get_cycle() + TIMEBASE
+}
+
+/// Fire the next timer interrupt of the current hart at `deadline`, or never if `None`.
+pub fn set_next_event(deadline: Option<u64>) {
+    let deadline = deadline.unwrap_or(u64::MAX);
+    NEXT_EVENT[hart_id()].store(deadline, Ordering::Relaxed);
+    set_timer(deadline);
+}
+
+/// Make sure the next timer interrupt of the current hart fires no later than `deadline`.
+pub fn set_next_event_before(deadline: u64) {
+    if deadline < NEXT_EVENT[hart_id()].load(Ordering::Relaxed) {
+        set_next_event(Some(deadline));
+    }
 }
 
 pub fn get_cycle() -> u64 {
     time::read() as u64
 }
+
+pub fn cycles_to_ns(cycles: u64) -> u64 {
+    cycles / CLOCK_FREQ * 1_000_000_000 + cycles % CLOCK_FREQ * 1_000_000_000 / CLOCK_FREQ
+}
+
+/// The nanoseconds since boot, which never go back
+pub fn monotonic_ns() -> u64 {
+    cycles_to_ns(get_cycle())
+}
+
+/// The nanoseconds since the Unix epoch, from the RTC read at boot
+pub fn realtime_ns() -> u64 {
+    BOOT_TIME_NS.load(Ordering::Relaxed) + monotonic_ns()
+}
@@ -45,6 +45,12 @@ trap_from_user:
     STORE x30, 30
     STORE x31, 31
 
+	# 来自用户态时 tp 为用户的值，从返回用户态时保存的位置恢复 hart id
+	csrr s0, sstatus
+	andi s0, s0, 1 << 8
+	bnez s0, 1f
+	LOAD tp, 35
+1:
 	csrrw s0, sscratch, x0
 	csrr s1, sstatus
 	csrr s2, sepc
@@ -66,12 +72,14 @@ trap_from_user:
 _to_user:
 	addi s0, sp, 36*XLENB
 	csrw sscratch, s0
+	# 下次陷入时 TrapFrame 的 scause 处保存着 hart id，用户的 tp 最后恢复
+	STORE tp, 35
+	LOAD x4, 4
 _to_kernel:
     csrw sstatus, s1
     csrw sepc, s2
 	LOAD x1, 1
 	LOAD x3, 3
-    LOAD x4, 4
     LOAD x5, 5
     LOAD x6, 6
     LOAD x7, 7
@@ -5,10 +5,30 @@ tests = {
     'lab2': (False, 'pmm_test.rs'),
     'lab3': (False, 'vm_test.rs'),
     'labuser': (True, 'test_test.rs'),
+    'wait': (True, 'wait_test.rs'),
     'lab5': (True, 'fork_test.rs'),
     'lab6': (True, 'stride_test.rs'),
     'lab7': (False, 'mutex_test.rs'),
+    'join': (False, 'join_test.rs'),
     'lab8': (True, 'pipe_test.rs'),
+    'thread': (True, 'thread_test.rs'),
+    'exec': (True, 'exec_test.rs'),
+    'efault': (True, 'efault_test.rs'),
+    'fault': (True, 'fault_test.rs'),
+    'stdio': (True, 'stdio_test.rs'),
+    'nice': (True, 'nice_test.rs'),
+    'wrap': (True, 'wrap_test.rs'),
+    'mlfq': (True, 'mlfq_test.rs'),
+    'rt': (True, 'rt_test.rs'),
+    'fifo': (True, 'fifo_test.rs'),
+    'tid': (True, 'tid_test.rs'),
+    'rusage': (True, 'rusage_test.rs'),
+    'signal': (True, 'signal_test.rs'),
+    'stop': (True, 'stop_test.rs'),
+    'session': (True, 'session_test.rs'),
+    'sleep': (True, 'sleep_test.rs'),
+    'timer': (True, 'timer_test.rs'),
+    'clock': (True, 'clock_test.rs'),
 }
 if sys.argv[1] == 'clean':
     os.system('rm lab*')
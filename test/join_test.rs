@@ -0,0 +1,63 @@
+global_asm!(include_str!("boot/entry64.asm"));
+global_asm!(include_str!("link_user.S"));
+
+use crate::consts::*;
+
+#[no_mangle]
+pub extern "C" fn rust_main() -> ! {
+    extern "C" {
+        fn end();
+    }
+    crate::memory::init(
+        ((end as usize - KERNEL_BEGIN_VADDR + KERNEL_BEGIN_PADDR) >> 12) + 1,
+        PHYSICAL_MEMORY_END >> 12,
+    );
+    crate::interrupt::init();
+    crate::fs::init();
+    crate::process::init();
+    crate::process::spawn_named("join_test", join_test);
+    crate::timer::init();
+    crate::smp::start_others();
+    crate::process::run();
+    loop {}
+}
+
+use crate::process::{sleep, spawn, spawn_named};
+use alloc::vec::Vec;
+
+fn join_test() {
+    // 每个线程计算一段的和，汇总后应与整体的和相同
+    let workers: Vec<_> = (0..4)
+        .map(|i| {
+            let name = alloc::format!("worker{}", i);
+            spawn_named(&name, move || (i * 100..(i + 1) * 100).sum::<usize>())
+        })
+        .collect();
+    let sum: usize = workers.into_iter().map(|worker| worker.join()).sum();
+    if sum != (0..400).sum() {
+        panic!("join_test1 fail");
+    }
+    // 等待一个还没有返回的线程
+    let sleeper = spawn(|| {
+        sleep(1);
+        42
+    });
+    println!("waiting for thread {}", sleeper.tid());
+    if sleeper.join() != 42 {
+        panic!("join_test2 fail");
+    }
+    println!("join_test pass.");
+}
+
+/*
+out put:
+
+thread 2 (worker0) exited, exit code = 0
+thread 3 (worker1) exited, exit code = 0
+thread 4 (worker2) exited, exit code = 0
+thread 5 (worker3) exited, exit code = 0
+waiting for thread 6
+thread 6 exited, exit code = 0
+join_test pass.
+thread 1 (join_test) exited, exit code = 0
+*/
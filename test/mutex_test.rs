@@ -82,7 +82,7 @@ fn philosopher(table: Arc<dyn Table>) {
                 p.eat(&table);
                 println!("{} iter {} end.", p.name, i);
             }
-        })
+        });
     }
 }
 
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sleep_ms, sys_clock_gettime as clock_gettime, sys_gettimeofday as gettimeofday, TimeSpec,
+    TimeVal, CLOCK_MONOTONIC, CLOCK_REALTIME, EINVAL,
+};
+
+// 2020-01-01 00:00:00 UTC
+const YEAR_2020: usize = 1_577_836_800;
+
+fn now(clock: usize) -> u64 {
+    let mut tp = TimeSpec::default();
+    if clock_gettime(clock, &mut tp) != 0 || tp.nsec >= 1_000_000_000 {
+        panic!("clock_test1 fail");
+    }
+    tp.sec as u64 * 1_000_000_000 + tp.nsec as u64
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 实际时间来自 RTC
+    let real = now(CLOCK_REALTIME);
+    if real / 1_000_000_000 < YEAR_2020 as u64 {
+        panic!("clock_test2 fail");
+    }
+    // 单调时钟不会倒退，睡眠之后两个时钟都前进了相应的时间
+    let start = now(CLOCK_MONOTONIC);
+    if now(CLOCK_MONOTONIC) < start {
+        panic!("clock_test3 fail");
+    }
+    sleep_ms(100);
+    if now(CLOCK_MONOTONIC) - start < 100_000_000 || now(CLOCK_REALTIME) - real < 100_000_000 {
+        panic!("clock_test4 fail");
+    }
+    // gettimeofday 与 CLOCK_REALTIME 一致
+    let mut tv = TimeVal::default();
+    gettimeofday(&mut tv);
+    let real = now(CLOCK_REALTIME) / 1_000;
+    let tv = tv.sec as u64 * 1_000_000 + tv.usec as u64;
+    if tv > real || real - tv > 1_000_000 {
+        panic!("clock_test5 fail");
+    }
+    let mut tp = TimeSpec::default();
+    if clock_gettime(100, &mut tp) != -EINVAL {
+        panic!("clock_test6 fail");
+    }
+    println!("clock_test pass.");
+    0
+}
+
+/*
+out put:
+
+clock_test pass.
+thread 1 exited, exit code = 0
+*/
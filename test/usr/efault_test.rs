@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::io::O_RDONLY;
+use user::syscall::{sys_open, sys_pipe, sys_read, sys_write, EFAULT};
+
+// 内核地址与未映射的地址
+const KERNEL_ADDR: usize = 0xffffffffc0200000;
+const UNMAPPED_ADDR: usize = 0x10;
+
+static READONLY: &str = "read only data\0";
+
+#[no_mangle]
+pub fn main() -> usize {
+    if sys_write(1, KERNEL_ADDR as *const u8, 1) != -EFAULT {
+        panic!("efault_test1 fail");
+    }
+    if sys_open(UNMAPPED_ADDR as *const u8, O_RDONLY) != -EFAULT {
+        panic!("efault_test2 fail");
+    }
+    let mut fds = [0i32; 2];
+    if sys_pipe(&mut fds) != 0 {
+        panic!("efault_test3 fail");
+    }
+    // 不能读入只读的内存
+    if sys_read(fds[0] as usize, READONLY.as_ptr(), 1) != -EFAULT {
+        panic!("efault_test4 fail");
+    }
+    if sys_write(fds[1] as usize, KERNEL_ADDR as *const u8, 1) != -EFAULT {
+        panic!("efault_test5 fail");
+    }
+    println!("efault_test pass.");
+    0
+}
+
+/*
+out put:
+
+efault_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::io::{O_CREAT, O_WRONLY};
+use user::syscall::{
+    sys_close as close, sys_exec as exec, sys_exit as exit, sys_fork as fork, sys_getpid as getpid,
+    sys_open as open, sys_wait as waitpid, sys_write as write, E2BIG, ECHILD, ENOENT, ENOEXEC,
+};
+
+#[no_mangle]
+pub fn main() -> isize {
+    let argv = ["rust/hello_world\0".as_ptr(), core::ptr::null()];
+    let pid = fork();
+    if pid == 0 {
+        println!("I am the child {}, exec a missing program..", getpid());
+        let missing = "rust/not_exist\0".as_ptr();
+        if exec(missing, argv.as_ptr(), core::ptr::null()) != -ENOENT {
+            panic!("exec_test1 fail");
+        }
+        // 不是 ELF 文件时 exec 失败返回，原来的程序继续运行
+        let text = "exec_text\0".as_ptr();
+        let fd = open(text, O_WRONLY | O_CREAT);
+        write(fd as usize, "not a program\n".as_ptr(), 14);
+        close(fd as i32);
+        if exec(text, argv.as_ptr(), core::ptr::null()) != -ENOEXEC {
+            panic!("exec_test5 fail");
+        }
+        // 参数太多放不下时 exec 失败返回
+        let mut long = [b'a'; 4000];
+        long[3999] = 0;
+        let mut many = [long.as_ptr(); 41];
+        many[40] = core::ptr::null();
+        if exec(argv[0], many.as_ptr(), core::ptr::null()) != -E2BIG {
+            panic!("exec_test6 fail");
+        }
+        println!("I am still the child {}, exec hello_world..", getpid());
+        exec(argv[0], argv.as_ptr(), core::ptr::null());
+        panic!("exec_test2 fail");
+    }
+    if pid <= 0 {
+        panic!("pid <= 0");
+    }
+    let mut code: i32 = -1;
+    if waitpid(pid as usize, &mut code) != pid || code != 0 {
+        panic!("exec_test3 fail");
+    }
+    if waitpid(pid as usize, &mut code) != -ECHILD {
+        panic!("exec_test4 fail");
+    }
+    println!("exec_test pass.");
+    exit(0);
+}
+
+/*
+out put:
+
+I am the child 2, exec a missing program..
+I am still the child 2, exec hello_world..
+Hello world! from user mode program!
+(ten lines in total)
+thread 2 exited, exit code = 0
+exec_test pass.
+thread 1 exited, exit code = 0
+*/
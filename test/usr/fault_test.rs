@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+#![feature(asm)]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{sys_exit as exit, sys_fork as fork, sys_wait as waitpid};
+
+// 被内核杀死的进程的退出码为 128 + 信号编号
+const KILLED_BY_SIGILL: i32 = 128 + 4;
+const KILLED_BY_SIGSEGV: i32 = 128 + 11;
+
+fn run_child(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut code: i32 = 0;
+    if waitpid(pid as usize, &mut code) != pid {
+        panic!("wait failed");
+    }
+    code
+}
+
+fn null_write() {
+    unsafe {
+        (0 as *mut usize).write_volatile(0x10384);
+    }
+}
+
+fn kernel_read() {
+    unsafe {
+        (0xffffffffc0200000 as *const usize).read_volatile();
+    }
+}
+
+fn illegal_instruction() {
+    unsafe {
+        asm!(".word 0" :::: "volatile");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    if run_child(null_write) != KILLED_BY_SIGSEGV {
+        panic!("fault_test1 fail");
+    }
+    if run_child(kernel_read) != KILLED_BY_SIGSEGV {
+        panic!("fault_test2 fail");
+    }
+    if run_child(illegal_instruction) != KILLED_BY_SIGILL {
+        panic!("fault_test3 fail");
+    }
+    println!("fault_test pass.");
+    0
+}
+
+/*
+out put:
+
+StorePageFault va = 0x0 instruction = ...
+process 2 killed by signal 11: StorePageFault va = 0x0 instruction = ...
+thread 2 exited, exit code = 139
+LoadPageFault va = 0xffffffffc0200000 instruction = ...
+process 3 killed by signal 11: LoadPageFault va = 0xffffffffc0200000 instruction = ...
+thread 3 exited, exit code = 139
+process 4 killed by signal 4: IllegalInstruction va = 0x0 instruction = ...
+thread 4 exited, exit code = 132
+fault_test pass.
+thread 1 exited, exit code = 0
+*/
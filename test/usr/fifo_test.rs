@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sleep_ms, sys_exit as exit, sys_fork as fork, sys_gettime as gettime,
+    sys_sched_setscheduler as sched_setscheduler, sys_wait as waitpid, SchedParam, SCHED_FIFO,
+};
+
+// 所有线程要在同一个 hart 上竞争：SMP=1 python3 test.py fifo
+
+fn fifo(priority: usize) {
+    let param = SchedParam {
+        priority,
+        ..Default::default()
+    };
+    if sched_setscheduler(0, SCHED_FIFO, Some(&param)) != 0 {
+        panic!("fifo_test1 fail");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // gettime 的单位为 1/50 秒
+    let start = gettime();
+    // a 先醒来并一直运行，b 醒来后排在 a 之后
+    let a = fork();
+    if a == 0 {
+        fifo(5);
+        sleep_ms(100);
+        while gettime() - start < 40 {}
+        println!("a");
+        exit((gettime() - start) as usize);
+    }
+    let b = fork();
+    if b == 0 {
+        fifo(5);
+        sleep_ms(200);
+        println!("b");
+        exit((gettime() - start) as usize);
+    }
+    // 更高优先级的线程醒来时抢占 a，之后 a 仍应先于 b 运行
+    let high = fork();
+    if high == 0 {
+        fifo(20);
+        sleep_ms(400);
+        println!("high");
+        exit(0);
+    }
+    let mut code_a: i32 = 0;
+    let mut code_b: i32 = 0;
+    let mut code: i32 = 0;
+    waitpid(a as usize, &mut code_a);
+    waitpid(b as usize, &mut code_b);
+    waitpid(high as usize, &mut code);
+    if code_b < code_a {
+        panic!("fifo_test2 fail");
+    }
+    println!("fifo_test pass.");
+    0
+}
+
+/*
+out put:
+
+high
+thread 4 exited, exit code = 0
+a
+thread 2 exited, exit code = 40
+b
+thread 3 exited, exit code = 40
+fifo_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sleep_ms, sys_exit as exit, sys_fork as fork, sys_getrusage as getrusage,
+    sys_gettime as gettime, sys_wait as waitpid, Rusage, TimeVal, RUSAGE_THREAD,
+};
+
+// 需要用 MLFQ 调度器运行：SCHEDULER=mlfq python3 test.py mlfq
+
+fn ms(tv: &TimeVal) -> usize {
+    tv.sec * 1000 + tv.usec / 1000
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 每个 hart 上有两个计算密集型的线程
+    const TOTAL: usize = 8;
+    // gettime 的单位为 1/50 秒
+    let MAX_TIME = 100;
+    let start_time = gettime();
+    for _ in 0..TOTAL {
+        if fork() == 0 {
+            while gettime() - start_time < MAX_TIME {
+                unsafe { core::ptr::read_volatile(&0u8) };
+            }
+            // 以平均每段连续运行的毫秒数作为退出码
+            let mut usage = Rusage::default();
+            getrusage(RUSAGE_THREAD, &mut usage);
+            exit(ms(&usage.ru_utime) / usage.ru_nivcsw.max(1));
+        }
+    }
+    // 交互式的线程每次只运行很短的时间就睡眠，从不用完时间片
+    for _ in 0..50 {
+        sleep_ms(10);
+    }
+    let mut usage = Rusage::default();
+    getrusage(RUSAGE_THREAD, &mut usage);
+    if usage.ru_nivcsw * 10 > usage.ru_nvcsw {
+        panic!("mlfq_test1 fail");
+    }
+    // 最高级的时间片只有 10ms，平均运行这么久说明计算密集型的线程已经降到了更低的级别
+    for _ in 0..TOTAL {
+        let mut code: i32 = 0;
+        waitpid(0, &mut code);
+        if code < 30 {
+            panic!("mlfq_test2 fail");
+        }
+    }
+    println!("mlfq_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 61
+thread 3 exited, exit code = 60
+thread 4 exited, exit code = 62
+thread 5 exited, exit code = 61
+thread 6 exited, exit code = 59
+thread 7 exited, exit code = 60
+thread 8 exited, exit code = 61
+thread 9 exited, exit code = 62
+mlfq_test pass.
+thread 1 exited, exit code = 0
+
+// 其它调度器每个时钟中断都会重新调度，退出码约为 10，无法通过测试
+*/
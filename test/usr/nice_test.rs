@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    getpriority, nice, sys_exit as exit, sys_fork as fork, sys_setpriority as setpriority,
+    sys_wait as waitpid, EINVAL, ESRCH, PRIO_PROCESS,
+};
+
+#[no_mangle]
+pub fn main() -> usize {
+    if getpriority(PRIO_PROCESS, 0) != 0 {
+        panic!("nice_test1 fail");
+    }
+    if nice(5) != 5 || nice(-2) != 3 {
+        panic!("nice_test2 fail");
+    }
+    // 超出范围的 nice 值被截断
+    setpriority(PRIO_PROCESS, 0, -30);
+    if getpriority(PRIO_PROCESS, 0) != -20 {
+        panic!("nice_test3 fail");
+    }
+    if setpriority(1, 0, 0) != -EINVAL || getpriority(PRIO_PROCESS, 999) != -ESRCH {
+        panic!("nice_test4 fail");
+    }
+    setpriority(PRIO_PROCESS, 0, 7);
+    let pid = fork();
+    if pid == 0 {
+        // 子进程继承 nice 值
+        exit(getpriority(PRIO_PROCESS, 0) as usize);
+    }
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code);
+    if code != 7 {
+        panic!("nice_test5 fail");
+    }
+    println!("nice_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 7
+nice_test pass.
+thread 1 exited, exit code = 0
+*/
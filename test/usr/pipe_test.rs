@@ -13,6 +13,8 @@ use user::syscall::{
     sys_read,
     sys_write,
     sys_fork,
+    sys_wait,
+    EPIPE,
 };
 use alloc::string::String;
 
@@ -27,13 +29,16 @@ pub fn main() -> usize {
         // close write end of pipe
         sys_close(pipefd[1]);
         let mut string = String::from("");
-        let ch: u8 = 0;
+        let mut buf = [0u8; 4];
         loop {
-            sys_read(pipefd[0] as usize, &ch as *const u8, 1);
-            if ch == 0 {
+            // read returns 0 once the write end is closed
+            let n = sys_read(pipefd[0] as usize, buf.as_ptr(), buf.len());
+            if n <= 0 {
                 break;
             }
-            string.push(ch as char);
+            for &ch in buf[..n as usize].iter() {
+                string.push(ch as char);
+            }
         }
         println!("message received in child process = {}", string);
     } else {
@@ -41,12 +46,32 @@ pub fn main() -> usize {
         // close read end of pipe
         sys_close(pipefd[0]);
         let string = String::from("Hello world!");
-        for ch in string.bytes() {
-            sys_write(pipefd[1] as usize, &ch as *const u8, 1);
+        if sys_write(pipefd[1] as usize, string.as_ptr(), string.len()) != string.len() as i64 {
+            panic!("pipe_test1 fail");
         }
-        let ch: u8 = 0;
-        sys_write(pipefd[1] as usize, &ch as *const u8, 1);
+        sys_close(pipefd[1]);
         println!("message sent to child process pid {}!", pid);
+        let mut code: i32 = 0;
+        sys_wait(pid as usize, &mut code);
+
+        // nobody reads from the pipe any more
+        sys_pipe(&mut pipefd);
+        sys_close(pipefd[0]);
+        if sys_write(pipefd[1] as usize, string.as_ptr(), string.len()) != -EPIPE {
+            panic!("pipe_test2 fail");
+        }
+        println!("pipe_test pass.");
     }
     0
 }
+
+/*
+out put:
+
+fd_read = 3, fd_write = 4
+message sent to child process pid 2!
+message received in child process = Hello world!
+thread 2 exited, exit code = 0
+pipe_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,109 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_exit as exit, sys_fork as fork, sys_gettime as gettime,
+    sys_sched_setscheduler as sched_setscheduler, sys_wait as waitpid, sys_yield as yield_now,
+    SchedParam, EBUSY, EINVAL, EPERM, SCHED_DEADLINE, SCHED_FIFO, SCHED_OTHER,
+};
+
+fn deadline(runtime: usize, deadline: usize, period: usize) -> SchedParam {
+    SchedParam {
+        runtime,
+        deadline,
+        period,
+        ..Default::default()
+    }
+}
+
+fn priority(priority: usize) -> SchedParam {
+    SchedParam {
+        priority,
+        ..Default::default()
+    }
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    if sched_setscheduler(0, SCHED_FIFO, Some(&priority(0))) != -EINVAL
+        || sched_setscheduler(0, SCHED_DEADLINE, Some(&deadline(2000, 1000, 1000))) != -EINVAL
+        || sched_setscheduler(0, 5, Some(&priority(1))) != -EINVAL
+        || sched_setscheduler(999, SCHED_FIFO, Some(&priority(1))) != -EPERM
+    {
+        panic!("rt_test1 fail");
+    }
+    // 接纳控制：带宽之和不能超过上限
+    if sched_setscheduler(
+        0,
+        SCHED_DEADLINE,
+        Some(&deadline(500_000, 1_000_000, 1_000_000)),
+    ) != 0
+    {
+        panic!("rt_test2 fail");
+    }
+    let pid = fork();
+    if pid == 0 {
+        if sched_setscheduler(
+            0,
+            SCHED_DEADLINE,
+            Some(&deadline(600_000, 1_000_000, 1_000_000)),
+        ) != -EBUSY
+            || sched_setscheduler(
+                0,
+                SCHED_DEADLINE,
+                Some(&deadline(400_000, 1_000_000, 1_000_000)),
+            ) != 0
+        {
+            exit(1);
+        }
+        exit(0);
+    }
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code);
+    if code != 0 {
+        panic!("rt_test3 fail");
+    }
+    // 每 100ms 运行一小段，调用 yield 表示本周期已完成
+    if sched_setscheduler(0, SCHED_DEADLINE, Some(&deadline(20_000, 100_000, 100_000))) != 0 {
+        panic!("rt_test4 fail");
+    }
+    let start = gettime();
+    for _ in 0..5 {
+        yield_now();
+    }
+    if gettime() - start < 15 {
+        panic!("rt_test5 fail");
+    }
+    // 回到普通调度类时不需要 param
+    if sched_setscheduler(0, SCHED_OTHER, None) != 0 {
+        panic!("rt_test6 fail");
+    }
+    // SCHED_FIFO 线程 yield 后仍然先于普通线程运行
+    sched_setscheduler(0, SCHED_FIFO, Some(&priority(10)));
+    let pid = fork();
+    if pid == 0 {
+        println!("child");
+        exit(0);
+    }
+    for _ in 0..10 {
+        yield_now();
+    }
+    println!("parent");
+    waitpid(pid as usize, &mut code);
+    println!("rt_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 0
+parent
+child
+thread 3 exited, exit code = 0
+rt_test pass.
+thread 1 exited, exit code = 0
+*/
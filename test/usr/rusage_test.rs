@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_exit as exit, sys_fork as fork, sys_getrusage as getrusage, sys_gettime as gettime,
+    sys_times as times, sys_wait as waitpid, sys_yield as yield_now, Rusage, Tms, EINVAL,
+    RUSAGE_CHILDREN, RUSAGE_SELF, RUSAGE_THREAD,
+};
+
+// 在用户态空转大约 ticks 个 tick
+fn spin(ticks: i64) {
+    let start = gettime();
+    while gettime() - start < ticks {
+        for _ in 0..1000 {
+            unsafe { core::ptr::read_volatile(&0u8) };
+        }
+    }
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut usage = Rusage::default();
+    if getrusage(2, &mut usage) != -EINVAL {
+        panic!("rusage_test1 fail");
+    }
+    spin(10);
+    for _ in 0..5 {
+        yield_now();
+    }
+    getrusage(RUSAGE_THREAD, &mut usage);
+    if usage.ru_utime.sec == 0 && usage.ru_utime.usec < 50_000 || usage.ru_nvcsw < 5 {
+        panic!("rusage_test2 fail");
+    }
+    let mut tms = Tms::default();
+    let now = times(&mut tms);
+    if now < 10 || tms.tms_utime < 5 || tms.tms_cutime != 0 {
+        panic!("rusage_test3 fail");
+    }
+    // 回收子进程后才计入 RUSAGE_CHILDREN
+    let pid = fork();
+    if pid == 0 {
+        spin(10);
+        exit(0);
+    }
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code);
+    getrusage(RUSAGE_CHILDREN, &mut usage);
+    if usage.ru_utime.sec == 0 && usage.ru_utime.usec < 100_000 || usage.ru_nwakeups != 0 {
+        panic!("rusage_test4 fail");
+    }
+    times(&mut tms);
+    if tms.tms_cutime < 5 {
+        panic!("rusage_test5 fail");
+    }
+    // 等待子进程时睡眠，被它的退出唤醒
+    getrusage(RUSAGE_SELF, &mut usage);
+    if usage.ru_nwakeups == 0 {
+        panic!("rusage_test6 fail");
+    }
+    println!("rusage_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 0
+rusage_test pass.
+thread 1 exited, exit code = 0
+*/
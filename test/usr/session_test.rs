@@ -0,0 +1,100 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_exit as exit, sys_fork as fork, sys_getpgid as getpgid, sys_getpid as getpid,
+    sys_getsid as getsid, sys_kill as kill, sys_read as read, sys_setpgid as setpgid,
+    sys_setsid as setsid, sys_sigaction, sys_waitpid as waitpid, sys_yield as yield_now, tcgetpgrp,
+    tcsetpgrp, wifstopped, wstopsig, SigAction, EINTR, EIO, ENOTTY, EPERM, SIGCONT, SIGTTIN,
+    SIG_IGN, WNOHANG, WUNTRACED,
+};
+
+fn wait(pid: i64, options: usize) -> i32 {
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code, options);
+    code
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 由内核启动的进程自成一组、自成一个会话，并拥有控制台
+    let pid = getpid();
+    if getpgid(0) != pid || getsid(0) != pid || tcgetpgrp(0) != pid {
+        panic!("session_test1 fail");
+    }
+    if setsid() != -EPERM || setpgid(0, 0) != -EPERM || tcsetpgrp(0, 1000) != -EPERM {
+        panic!("session_test2 fail");
+    }
+    // 不是组长的子进程可以新建会话，新会话没有控制台
+    let child = fork();
+    if child == 0 {
+        let me = getpid();
+        if getpgid(0) != pid || setsid() != me || getsid(0) != me || getpgid(0) != me {
+            exit(1);
+        }
+        exit(if tcgetpgrp(0) == -ENOTTY { 0 } else { 2 });
+    }
+    if wait(child, 0) != 0 {
+        panic!("session_test3 fail");
+    }
+    // 后台进程组读控制台时被 SIGTTIN 停止，忽略 SIGTTIN 时读失败
+    let child = fork();
+    if child == 0 {
+        setpgid(0, 0);
+        let mut buf = [0u8; 1];
+        if read(0, buf.as_mut_ptr(), 1) != -EINTR {
+            exit(1);
+        }
+        sys_sigaction(SIGTTIN, Some(&SigAction::new(SIG_IGN, 0, 0)), None);
+        if read(0, buf.as_mut_ptr(), 1) != -EIO {
+            exit(2);
+        }
+        exit(0);
+    }
+    setpgid(child as usize, 0);
+    if getpgid(child as usize) != child {
+        panic!("session_test4 fail");
+    }
+    let code = wait(child, WUNTRACED);
+    if !wifstopped(code) || wstopsig(code) != SIGTTIN {
+        panic!("session_test5 fail");
+    }
+    let mut code: i32 = 0;
+    if waitpid(child as usize, &mut code, WNOHANG | WUNTRACED) != 0 {
+        panic!("session_test6 fail");
+    }
+    kill(-child as isize, SIGCONT);
+    if wait(child, 0) != 0 {
+        panic!("session_test7 fail");
+    }
+    // 前台进程组交给子进程后再取回
+    let child = fork();
+    if child == 0 {
+        while tcgetpgrp(0) != getpid() {
+            yield_now();
+        }
+        exit(0);
+    }
+    setpgid(child as usize, 0);
+    if tcsetpgrp(0, child as usize) != 0 || wait(child, 0) != 0 || tcsetpgrp(0, pid as usize) != 0 {
+        panic!("session_test8 fail");
+    }
+    if kill(0, 0) != 0 || tcgetpgrp(0) != pid {
+        panic!("session_test9 fail");
+    }
+    println!("session_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 0
+thread 3 exited, exit code = 0
+thread 4 exited, exit code = 0
+session_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,133 @@
+#![no_std]
+#![no_main]
+#![feature(asm)]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sigmask, signal, sys_exit as exit, sys_fork as fork, sys_getpid as getpid, sys_kill as kill,
+    sys_sigaction as sigaction, sys_sigprocmask as sigprocmask, sys_wait as waitpid,
+    sys_yield as yield_now, SigAction, EINVAL, SIGCONT, SIGKILL, SIGSEGV, SIGSTOP, SIGTERM,
+    SIGUSR1, SIG_BLOCK, SIG_IGN, SIG_UNBLOCK,
+};
+
+static mut RECEIVED: usize = 0;
+
+extern "C" fn handler(sig: usize) {
+    unsafe {
+        RECEIVED += sig;
+    }
+}
+
+extern "C" fn exit_handler(_sig: usize) {
+    exit(5);
+}
+
+fn received() -> usize {
+    unsafe { core::ptr::read_volatile(&RECEIVED) }
+}
+
+fn wait(pid: i64) -> i32 {
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code);
+    code
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let pid = getpid() as isize;
+    // 处理函数返回后被打断的代码照常运行，kill 的返回值不变
+    signal(SIGUSR1, handler);
+    if kill(pid, SIGUSR1) != 0 || received() != SIGUSR1 {
+        panic!("signal_test1 fail");
+    }
+    // 阻塞期间信号保持未决，解除阻塞后送达
+    let set = sigmask(SIGUSR1);
+    sigprocmask(SIG_BLOCK, Some(&set), None);
+    kill(pid, SIGUSR1);
+    if received() != SIGUSR1 {
+        panic!("signal_test2 fail");
+    }
+    sigprocmask(SIG_UNBLOCK, Some(&set), None);
+    if received() != 2 * SIGUSR1 {
+        panic!("signal_test3 fail");
+    }
+    let mut old = SigAction::default();
+    let ignore = SigAction::new(SIG_IGN, 0, 0);
+    if sigaction(SIGKILL, Some(&ignore), None) != -EINVAL
+        || sigaction(SIGTERM, Some(&ignore), Some(&mut old)) != 0
+        || old.handler != 0
+    {
+        panic!("signal_test4 fail");
+    }
+    kill(pid, SIGTERM);
+    // 默认动作为终止进程
+    let child = fork();
+    if child == 0 {
+        let dfl = SigAction::default();
+        sigaction(SIGTERM, Some(&dfl), None);
+        loop {
+            yield_now();
+        }
+    }
+    kill(child as isize, SIGTERM);
+    if wait(child) != 128 + SIGTERM as i32 {
+        panic!("signal_test5 fail");
+    }
+    // 停止的进程在继续之后才处理信号
+    let child = fork();
+    if child == 0 {
+        signal(SIGUSR1, exit_handler);
+        loop {
+            yield_now();
+        }
+    }
+    kill(child as isize, SIGSTOP);
+    kill(child as isize, SIGUSR1);
+    kill(child as isize, SIGCONT);
+    if wait(child) != 5 {
+        panic!("signal_test6 fail");
+    }
+    let child = fork();
+    if child == 0 {
+        loop {}
+    }
+    kill(child as isize, SIGSTOP);
+    kill(child as isize, SIGKILL);
+    if wait(child) != 128 + SIGKILL as i32 {
+        panic!("signal_test7 fail");
+    }
+    // 栈指针太小放不下信号帧时进程被 SIGSEGV 杀死
+    let child = fork();
+    if child == 0 {
+        signal(SIGUSR1, handler);
+        let me = getpid() as usize;
+        unsafe {
+            asm!(
+                "mv sp, $0\n ecall"
+                :
+                : "r"(16), "{x17}"(129), "{x10}"(me), "{x11}"(SIGUSR1)
+                : "memory"
+                : "volatile"
+            );
+        }
+        exit(0);
+    }
+    if wait(child) != 128 + SIGSEGV as i32 {
+        panic!("signal_test8 fail");
+    }
+    println!("signal_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 143
+thread 3 exited, exit code = 5
+thread 4 exited, exit code = 137
+thread 5 exited, exit code = 139
+signal_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    signal, sleep_ms, sys_exit as exit, sys_fork as fork, sys_gettime as gettime, sys_kill as kill,
+    sys_nanosleep as nanosleep, sys_wait as waitpid, TimeSpec, CLOCKS_PER_SEC, EINTR, EINVAL,
+    SIGUSR1,
+};
+
+extern "C" fn handler(_sig: usize) {}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 睡眠时间不会比要求的短
+    let start = gettime();
+    sleep_ms(100);
+    if gettime() - start < CLOCKS_PER_SEC / 10 {
+        panic!("sleep_test1 fail");
+    }
+    let bad = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    if nanosleep(&bad, None) != -EINVAL {
+        panic!("sleep_test2 fail");
+    }
+    // 被信号打断时返回剩余的时间
+    let pid = fork();
+    if pid == 0 {
+        signal(SIGUSR1, handler);
+        let req = TimeSpec { sec: 10, nsec: 0 };
+        let mut rem = TimeSpec::default();
+        if nanosleep(&req, Some(&mut rem)) != -EINTR || rem.sec >= 10 || rem.sec == 0 {
+            exit(1);
+        }
+        exit(0);
+    }
+    sleep_ms(50);
+    kill(pid as isize, SIGUSR1);
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code);
+    if code != 0 {
+        panic!("sleep_test3 fail");
+    }
+    println!("sleep_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 0
+sleep_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::io::{STDERR, STDIN, STDOUT};
+use user::syscall::{sys_read, sys_write, EBADF};
+
+#[no_mangle]
+pub fn main() -> usize {
+    let s = "one syscall for the whole line\n";
+    if sys_write(STDOUT, s.as_ptr(), s.len()) != s.len() as i64 {
+        panic!("stdio_test1 fail");
+    }
+    let s = "and stderr works too\n";
+    if sys_write(STDERR, s.as_ptr(), s.len()) != s.len() as i64 {
+        panic!("stdio_test2 fail");
+    }
+    let mut buf = [0u8; 16];
+    if sys_read(STDOUT, buf.as_mut_ptr(), buf.len()) != -EBADF {
+        panic!("stdio_test3 fail");
+    }
+    if sys_write(STDIN, buf.as_ptr(), buf.len()) != -EBADF {
+        panic!("stdio_test4 fail");
+    }
+    eprintln!("eprintln writes to fd {}", STDERR);
+    println!("stdio_test pass.");
+    0
+}
+
+/*
+out put:
+
+one syscall for the whole line
+and stderr works too
+eprintln writes to fd 2
+stdio_test pass.
+thread 1 exited, exit code = 0
+*/
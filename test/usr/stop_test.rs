@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::io::getc;
+use user::syscall::{
+    sys_exit as exit, sys_fork as fork, sys_kill as kill, sys_waitpid as waitpid,
+    sys_yield as yield_now, wifstopped, wstopsig, SIGCONT, SIGINT, SIGTSTP, WUNTRACED,
+};
+
+fn wait(pid: i64, options: usize) -> i32 {
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code, options);
+    code
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // Ctrl-Z 停止的子进程，等待时只报告一次
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_now();
+        }
+    }
+    kill(pid as isize, SIGTSTP);
+    let code = wait(pid, WUNTRACED);
+    if !wifstopped(code) || wstopsig(code) != SIGTSTP {
+        panic!("stop_test1 fail");
+    }
+    kill(pid as isize, SIGCONT);
+    kill(pid as isize, SIGINT);
+    if wait(pid, WUNTRACED) != 128 + SIGINT as i32 {
+        panic!("stop_test2 fail");
+    }
+    // 等待输入的进程也能被 Ctrl-C 终止
+    let pid = fork();
+    if pid == 0 {
+        getc();
+        exit(0);
+    }
+    for _ in 0..10 {
+        yield_now();
+    }
+    kill(pid as isize, SIGINT);
+    if wait(pid, 0) != 128 + SIGINT as i32 {
+        panic!("stop_test3 fail");
+    }
+    println!("stop_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 130
+thread 3 exited, exit code = 130
+stop_test pass.
+thread 1 exited, exit code = 0
+*/
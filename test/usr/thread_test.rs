@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{sys_getpid as getpid, sys_yield as yield_now, thread_create};
+
+const STACK_SIZE: usize = 0x4000;
+static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+static mut SHARED: usize = 0;
+
+fn child(arg: usize) -> usize {
+    println!("I am the new thread in process {}, arg = {:x}", getpid(), arg);
+    unsafe {
+        SHARED = arg;
+    }
+    0
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let magic: usize = 0x10384;
+    println!("I am the main thread in process {}", getpid());
+    let tid = thread_create(child, magic, unsafe { &mut STACK });
+    if tid <= 0 {
+        panic!("thread_create fail");
+    }
+    while unsafe { core::ptr::read_volatile(&SHARED) } != magic {
+        yield_now();
+    }
+    println!("thread_test pass.");
+    0
+}
+
+/*
+out put:
+
+I am the main thread in process 1
+I am the new thread in process 1, arg = 10384
+thread 2 exited, exit code = 0
+thread_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{sys_exit as exit, sys_fork as fork, sys_wait as waitpid};
+
+// 超过线程表初始的 100 项
+const N: usize = 120;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut last = 0;
+    let mut code: i32 = 0;
+    for i in 0..N {
+        let pid = fork();
+        if pid == 0 {
+            exit(i);
+        }
+        if pid < 0 {
+            panic!("tid_test1 fail");
+        }
+        // 刚回收的 tid 不会马上分配出去
+        if pid as usize == last {
+            panic!("tid_test2 fail");
+        }
+        if waitpid(pid as usize, &mut code) != pid || code != i as i32 {
+            panic!("tid_test3 fail");
+        }
+        last = pid as usize;
+    }
+    println!("tid_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 0
+thread 3 exited, exit code = 1
+...
+thread 99 exited, exit code = 97
+thread 2 exited, exit code = 98
+...
+thread 23 exited, exit code = 119
+tid_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -0,0 +1,113 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    alarm, signal, sleep_ms, sys_close as close, sys_exit as exit, sys_fork as fork,
+    sys_getitimer as getitimer, sys_gettime as gettime, sys_pipe as pipe, sys_ppoll as ppoll,
+    sys_read as read, sys_setitimer as setitimer, sys_wait as waitpid, sys_write as write,
+    ItimerVal, PollFd, TimeSpec, TimeVal, CLOCKS_PER_SEC, ITIMER_REAL, POLLHUP, POLLIN, POLLNVAL,
+    SIGALRM,
+};
+
+static mut ALARMS: usize = 0;
+
+extern "C" fn on_alarm(_sig: usize) {
+    unsafe {
+        ALARMS += 1;
+    }
+}
+
+fn alarms() -> usize {
+    unsafe { core::ptr::read_volatile(&ALARMS) }
+}
+
+fn poll_one(fd: i32, timeout_ms: Option<usize>) -> (i64, i16) {
+    let mut fds = [PollFd {
+        fd,
+        events: POLLIN,
+        revents: 0,
+    }];
+    let timeout = timeout_ms.map(|ms| TimeSpec {
+        sec: ms / 1000,
+        nsec: ms % 1000 * 1_000_000,
+    });
+    let ready = ppoll(&mut fds, timeout.as_ref());
+    (ready, fds[0].revents)
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    signal(SIGALRM, on_alarm);
+    // alarm 返回上一次设置的剩余秒数
+    if alarm(10) != 0 || alarm(0) != 10 {
+        panic!("timer_test1 fail");
+    }
+    // 周期性的定时器，每次 SIGALRM 都会打断睡眠
+    let period = TimeVal {
+        sec: 0,
+        usec: 50_000,
+    };
+    let timer = ItimerVal {
+        interval: period,
+        value: period,
+    };
+    setitimer(ITIMER_REAL, &timer, None);
+    while alarms() < 3 {
+        sleep_ms(1000);
+    }
+    let mut curr = ItimerVal::default();
+    getitimer(ITIMER_REAL, &mut curr);
+    if curr.interval.usec != 50_000 || curr.value.sec != 0 || curr.value.usec == 0 {
+        panic!("timer_test2 fail");
+    }
+    // 取消之后不再收到 SIGALRM
+    setitimer(ITIMER_REAL, &ItimerVal::default(), None);
+    let n = alarms();
+    sleep_ms(200);
+    getitimer(ITIMER_REAL, &mut curr);
+    if alarms() != n || curr.value.usec != 0 {
+        panic!("timer_test3 fail");
+    }
+    // 等待管道超时
+    let mut pipefd: [i32; 2] = [0; 2];
+    pipe(&mut pipefd);
+    let start = gettime();
+    if poll_one(pipefd[0], Some(100)) != (0, 0) || gettime() - start < CLOCKS_PER_SEC / 10 {
+        panic!("timer_test4 fail");
+    }
+    if poll_one(100, Some(0)) != (1, POLLNVAL) {
+        panic!("timer_test5 fail");
+    }
+    // 另一个进程写入后，等待中的 ppoll 提前返回
+    let pid = fork();
+    if pid == 0 {
+        sleep_ms(50);
+        write(pipefd[1] as usize, "x".as_ptr(), 1);
+        exit(0);
+    }
+    if poll_one(pipefd[0], Some(10_000)) != (1, POLLIN) {
+        panic!("timer_test6 fail");
+    }
+    let mut code: i32 = 0;
+    waitpid(pid as usize, &mut code);
+    // 读完并关闭写端之后报告挂断
+    let mut buf = [0u8; 1];
+    read(pipefd[0] as usize, buf.as_mut_ptr(), 1);
+    close(pipefd[1]);
+    if poll_one(pipefd[0], None) != (1, POLLHUP) {
+        panic!("timer_test7 fail");
+    }
+    println!("timer_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 2 exited, exit code = 0
+timer_test pass.
+thread 1 exited, exit code = 0
+*/
@@ -4,7 +4,10 @@
 #[macro_use]
 extern crate user;
 
-use user::syscall::{sys_exit as exit, sys_yield as yield_now, sys_fork as fork, sys_wait as waitpid};
+use user::syscall::{
+    sleep_ms, sys_exit as exit, sys_fork as fork, sys_wait as waitpid, sys_waitpid,
+    sys_yield as yield_now, WNOHANG,
+};
 
 #[no_mangle]
 pub fn main() -> isize {
@@ -15,6 +18,7 @@ pub fn main() -> isize {
     pid = fork() as usize;
     if (pid == 0) {
         println!("I am the child.");
+        sleep_ms(100);
         yield_now();
         yield_now();
         yield_now();
@@ -30,10 +34,15 @@ pub fn main() -> isize {
     if pid <= 0 {
         panic!("pid <= 0");
     }
+    // 子进程还在运行时 WNOHANG 返回 0
+    if sys_waitpid(pid, &mut code, WNOHANG) != 0 {
+        panic!("wait_test3 fail");
+    }
     println!("I am the parent, waiting now..");
-    let wait_pid = waitpid(pid, &mut code);
+    // pid 为 0 时等待任意一个子进程，返回它的 pid
+    let wait_pid = waitpid(0, &mut code);
     println!("{}, {:x}", wait_pid, code);
-    if wait_pid != 0 || code != magic as i32 {
+    if wait_pid != pid as i64 || code != magic as i32 {
         panic!("wait_test1 fail");
     }
     if !(waitpid(pid, &mut code) != 0) {
@@ -52,6 +61,7 @@ I am the child.
 I am parent, fork a child pid 2
 I am the parent, waiting now..
 thread 2 exited, exit code = 66436
+2, 10384
 waitpid 2 ok.
 wait_test pass.
 thread 1 exited, exit code = 0
@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    nice, sys_exit as exit, sys_fork as fork, sys_gettime as gettime, sys_wait as waitpid,
+};
+
+fn spin_delay() {
+    let mut j = true;
+    for i in 0..10 {
+        j = !j;
+    }
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 线程数是默认 hart 数的两倍，nice 为 0 和 10 的线程交替创建
+    const TOTAL: usize = 8;
+    // nice 为 0 的线程每个时钟中断 stride 增加约 2^25，运行 3 秒足以让每个线程的 stride 溢出回绕
+    // gettime 的单位为 1/50 秒
+    let MAX_TIME = 150;
+    let start_time = gettime();
+    let mut pids = [0; TOTAL];
+    for i in 0..TOTAL {
+        let pid = fork();
+        if pid == 0 {
+            nice((i % 2 * 10) as isize);
+            let mut acc = 0;
+            loop {
+                spin_delay();
+                acc += 1;
+                if acc % 400 == 0 {
+                    let time = gettime() - start_time;
+                    if time > MAX_TIME {
+                        exit(acc);
+                    }
+                }
+            }
+        }
+        pids[i] = pid;
+    }
+    let mut acc = [0; 2];
+    for _ in 0..TOTAL {
+        let mut code: i32 = 0;
+        let pid = waitpid(0, &mut code);
+        let i = pids
+            .iter()
+            .position(|&p| p == pid)
+            .expect("wrap_test1 fail");
+        acc[i % 2] += code as usize;
+    }
+    println!("nice 0: {}, nice 10: {}", acc[0], acc[1]);
+    // nice 0 的权重约是 nice 10 的 9 倍，同一个 hart 上竞争时差距应当明显
+    if acc[1] == 0 || acc[0] < acc[1] * 2 {
+        panic!("wrap_test2 fail");
+    }
+    println!("wrap_test pass.");
+    0
+}
+
+/*
+out put:
+
+thread 3 exited, exit code = 24400
+thread 2 exited, exit code = 219200
+thread 5 exited, exit code = 25200
+thread 4 exited, exit code = 221600
+thread 7 exited, exit code = 24000
+thread 6 exited, exit code = 218400
+thread 9 exited, exit code = 24800
+thread 8 exited, exit code = 220400
+nice 0: 879600, nice 10: 98400
+wrap_test pass.
+thread 1 exited, exit code = 0
+
+// 输出的数字只是示意，每次运行会有所不同
+*/
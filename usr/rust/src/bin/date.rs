@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{sys_clock_gettime, TimeSpec, CLOCK_REALTIME};
+
+// 由 1970-01-01 以来的天数求公历日期
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut now = TimeSpec::default();
+    if sys_clock_gettime(CLOCK_REALTIME, &mut now) < 0 {
+        println!("date: cannot read the clock");
+        return 1;
+    }
+    let secs = now.sec as i64;
+    let (year, month, day) = civil_from_days(secs / 86400);
+    let time = secs % 86400;
+    println!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    );
+    0
+}
@@ -10,12 +10,220 @@ const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use user::io::getc;
-use user::syscall::sys_exec;
+use user::syscall::{
+    sys_exec, sys_exit, sys_fork, sys_getpgid, sys_getpid, sys_kill, sys_setpgid, sys_sigaction,
+    sys_waitpid, tcsetpgrp, wifstopped, SigAction, ENOENT, SIGCONT, SIGINT, SIGTSTP, SIG_DFL,
+    SIG_IGN, WNOHANG, WUNTRACED,
+};
+
+// 设置 Ctrl-C 与 Ctrl-Z 对应信号的处理方式
+fn set_job_signals(handler: usize) {
+    let action = SigAction::new(handler, 0, 0);
+    sys_sigaction(SIGINT, Some(&action), None);
+    sys_sigaction(SIGTSTP, Some(&action), None);
+}
+
+// shell 启动的一个程序，自成一个进程组，组号即为它的 pid
+struct Job {
+    id: usize,
+    pid: usize,
+    cmd: String,
+    stopped: bool,
+}
+
+struct Shell {
+    // shell 自己的进程组，作业结束或停止后取回控制台
+    pgid: usize,
+    // 按编号排列的后台与停止的作业
+    jobs: Vec<Job>,
+}
+
+impl Shell {
+    fn new() -> Self {
+        Shell {
+            pgid: sys_getpgid(0) as usize,
+            jobs: Vec::new(),
+        }
+    }
+
+    // 以 & 结尾的命令在后台运行，jobs、fg、bg 为内建命令
+    fn run(&mut self, line: &str) {
+        let mut line = line.trim();
+        let background = line.ends_with('&');
+        if background {
+            line = line[..line.len() - 1].trim_end();
+        }
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("jobs") => self.list(),
+            Some("fg") => self.fg(words.next()),
+            Some("bg") => self.bg(words.next()),
+            Some(_) => self.exec(line, background),
+            None => {}
+        }
+    }
+
+    // 以空白分隔命令行，第一项为程序路径，全部各项作为 argv 传给程序
+    // 在 fork 出的子进程中 exec，前台运行时等待其退出或被 Ctrl-Z 停止
+    fn exec(&mut self, line: &str, background: bool) {
+        let args: Vec<String> = line
+            .split_whitespace()
+            .map(|arg| {
+                let mut arg = String::from(arg);
+                arg.push('\0');
+                arg
+            })
+            .collect();
+        let mut argv: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+        argv.push(core::ptr::null());
+        println!("searching for program {}", args[0]);
+        let pid = sys_fork();
+        if pid == 0 {
+            // 父子进程都设置一次进程组与前台进程组，无论谁先运行都不会出错
+            sys_setpgid(0, 0);
+            if !background {
+                tcsetpgrp(0, sys_getpid() as usize);
+            }
+            // shell 忽略的信号在子进程中恢复默认处理
+            set_job_signals(SIG_DFL);
+            // exec 成功时不会返回
+            let err = sys_exec(args[0].as_ptr(), argv.as_ptr(), core::ptr::null());
+            if err == -ENOENT {
+                println!("command not found!");
+            } else {
+                println!("exec failed, errno = {}", -err);
+            }
+            sys_exit(1);
+        } else if pid > 0 {
+            let pid = pid as usize;
+            sys_setpgid(pid, pid);
+            let job = Job {
+                id: 0,
+                pid,
+                cmd: String::from(line),
+                stopped: false,
+            };
+            if background {
+                let id = self.add(job);
+                println!("[{}] {}", id, pid);
+            } else {
+                self.foreground(job);
+            }
+        }
+    }
+
+    // 加入作业列表，返回作业编号
+    fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+        let id = job.id;
+        self.jobs.push(job);
+        self.jobs.sort_by_key(|job| job.id);
+        id
+    }
+
+    // 取出编号为 %n 或 n 的作业，没有给出编号时取最后一个
+    fn take(&mut self, arg: Option<&str>) -> Option<Job> {
+        let index = match arg {
+            Some(arg) => {
+                let id = arg.trim_start_matches('%').parse::<usize>().ok()?;
+                self.jobs.iter().position(|job| job.id == id)?
+            }
+            None => self.jobs.len().checked_sub(1)?,
+        };
+        Some(self.jobs.remove(index))
+    }
+
+    // 把作业交给控制台并等待它，停止的作业先继续运行
+    fn foreground(&mut self, mut job: Job) {
+        tcsetpgrp(0, job.pid);
+        if job.stopped {
+            job.stopped = false;
+            sys_kill(-(job.pid as isize), SIGCONT);
+        }
+        let mut code: i32 = 0;
+        sys_waitpid(job.pid, &mut code, WUNTRACED);
+        tcsetpgrp(0, self.pgid);
+        if wifstopped(code) {
+            job.stopped = true;
+            let id = self.add(job);
+            let job = self.jobs.iter().find(|job| job.id == id).unwrap();
+            println!("[{}] stopped  {}", job.id, job.cmd);
+        }
+    }
+
+    fn fg(&mut self, arg: Option<&str>) {
+        match self.take(arg) {
+            Some(job) => {
+                println!("{}", job.cmd);
+                self.foreground(job);
+            }
+            None => println!("fg: no such job"),
+        }
+    }
+
+    fn bg(&mut self, arg: Option<&str>) {
+        match self.take(arg) {
+            Some(mut job) => {
+                if job.stopped {
+                    job.stopped = false;
+                    sys_kill(-(job.pid as isize), SIGCONT);
+                    println!("[{}] {} &", job.id, job.cmd);
+                } else {
+                    println!("bg: job {} already in background", job.id);
+                }
+                self.add(job);
+            }
+            None => println!("bg: no such job"),
+        }
+    }
+
+    fn list(&mut self) {
+        self.reap();
+        for job in self.jobs.iter() {
+            let state = if job.stopped { "stopped" } else { "running" };
+            println!("[{}] {}  {}", job.id, state, job.cmd);
+        }
+    }
+
+    // 回收结束的后台作业，报告在后台停止的作业，例如读控制台的作业
+    fn reap(&mut self) {
+        let mut i = 0;
+        while i < self.jobs.len() {
+            let job = &mut self.jobs[i];
+            let mut code: i32 = 0;
+            match sys_waitpid(job.pid, &mut code, WNOHANG | WUNTRACED) {
+                // 还在运行
+                0 => {}
+                pid if pid > 0 && wifstopped(code) => {
+                    job.stopped = true;
+                    println!("[{}] stopped  {}", job.id, job.cmd);
+                }
+                pid if pid > 0 => {
+                    println!("[{}] done, exit code = {}  {}", job.id, code, job.cmd);
+                    self.jobs.remove(i);
+                    continue;
+                }
+                // 已经被回收
+                _ => {
+                    self.jobs.remove(i);
+                    continue;
+                }
+            }
+            i += 1;
+        }
+    }
+}
 
 #[no_mangle]
 pub fn main() {
     println!("Rust user shell");
+    // shell 在前台读命令时忽略 Ctrl-C 与 Ctrl-Z
+    set_job_signals(SIG_IGN);
+    let mut shell = Shell::new();
     let mut line: String = String::new();
     print!(">> ");
     loop {
@@ -24,11 +232,10 @@ pub fn main() {
             LF | CR => {
                 println!("");
                 if !line.is_empty() {
-                    line.push('\0');
-                    println!("searching for program {}", line);
-                    sys_exec(line.as_ptr());
+                    shell.run(line.as_str());
                     line.clear();
                 }
+                shell.reap();
                 print!(">> ");
             }
             _ => {
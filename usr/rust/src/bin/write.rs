@@ -7,25 +7,33 @@ extern crate alloc;
 extern crate user;
 
 use user::io::*;
-use user::syscall::{sys_close, sys_open, sys_read, sys_write};
+use user::syscall::{from_cstr, sys_close, sys_open, sys_read, sys_write};
 
 const BUFFER_SIZE: usize = 20;
 const FILE: &'static str = "temp\0";
 const TEXT: &'static str = "Hello world!\0";
 
 #[no_mangle]
-pub fn main() -> usize {
-    // 将字符串写到文件 temp 中
-    let write_fd = sys_open(FILE.as_ptr(), O_WRONLY);
+pub fn main(argc: usize, argv: *const *const u8) -> usize {
+    // 文件名由第一个参数给出，默认为 temp
+    let file = if argc > 1 {
+        unsafe { *argv.add(1) }
+    } else {
+        FILE.as_ptr()
+    };
+    let name = unsafe { from_cstr(file) };
+
+    // 将字符串写到文件中
+    let write_fd = sys_open(file, O_WRONLY);
     sys_write(write_fd as usize, TEXT.as_ptr(), TEXT.len());
-    println!("write to file 'temp' successfully...");
+    println!("write to file '{}' successfully...", name);
     sys_close(write_fd as i32);
 
-    // 将字符串从文件 temp 读入内存
-    let read_fd = sys_open(FILE.as_ptr(), O_RDONLY);
+    // 将字符串从文件读入内存
+    let read_fd = sys_open(file, O_RDONLY);
     let read = [0u8; BUFFER_SIZE];
     sys_read(read_fd as usize, &read[0] as *const u8, BUFFER_SIZE);
-    println!("read from file 'temp' successfully...");
+    println!("read from file '{}' successfully...", name);
 
     // 检查功能是否正确
     let len = (0..BUFFER_SIZE).find(|&i| read[i] as u8 == 0).unwrap();
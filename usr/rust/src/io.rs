@@ -1,15 +1,18 @@
-use crate::syscall::sys_read;
-use crate::syscall::sys_write;
+use crate::syscall::{sys_read, sys_write, EINTR};
 use core::fmt::{self, Write};
 
 pub fn putchar(ch: char) {
-    sys_write(STDOUT, &ch as *const char as *const u8, 1);
+    let mut buf = [0u8; 4];
+    puts(ch.encode_utf8(&mut buf));
 }
 
+// 整个字符串只需一次系统调用
 pub fn puts(s: &str) {
-    for ch in s.chars() {
-        putchar(ch);
-    }
+    sys_write(STDOUT, s.as_ptr(), s.len());
+}
+
+pub fn eputs(s: &str) {
+    sys_write(STDERR, s.as_ptr(), s.len());
 }
 
 #[macro_export]
@@ -25,7 +28,21 @@ macro_rules! println {
     ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
 }
 
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ({
+        $crate::io::_eprint(format_args!($($arg)*));
+    });
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    () => ($crate::eprint!("\n"));
+    ($($arg:tt)*) => ($crate::eprint!("{}\n", format_args!($($arg)*)));
+}
+
 struct Stdout;
+struct Stderr;
 
 impl fmt::Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
@@ -34,17 +51,34 @@ impl fmt::Write for Stdout {
     }
 }
 
+impl fmt::Write for Stderr {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        eputs(s);
+        Ok(())
+    }
+}
+
 pub fn _print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+pub fn _eprint(args: fmt::Arguments) {
+    Stderr.write_fmt(args).unwrap();
+}
+
 pub const STDIN: usize = 0;
 pub const STDOUT: usize = 1;
+pub const STDERR: usize = 2;
 
+// 等待输入时被信号打断则重新读取
 pub fn getc() -> u8 {
     let mut c = 0u8;
-    assert_eq!(sys_read(STDIN, &mut c, 1), 1);
-    c
+    loop {
+        match sys_read(STDIN, &mut c, 1) {
+            1 => return c,
+            ret => assert_eq!(ret, -EINTR),
+        }
+    }
 }
 
 pub const O_RDONLY: i32 = 0; // 只读
@@ -52,3 +86,4 @@ pub const O_WRONLY: i32 = 1; // 只写
 pub const O_RDWR: i32 = 2; // 可读可写
 pub const O_CREAT: i32 = 64; // 打开文件时若文件不存在，创建它
 pub const O_APPEND: i32 = 1024; // 从文件结尾开始写入
+pub const O_CLOEXEC: i32 = 0x80000; // exec 时关闭该文件
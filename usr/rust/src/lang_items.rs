@@ -4,7 +4,7 @@ use core::panic::PanicInfo;
 
 #[linkage = "weak"]
 #[no_mangle]
-fn main() -> usize {
+fn main(_argc: usize, _argv: *const *const u8) -> usize {
     panic!("No main() linked");
 }
 
@@ -24,7 +24,7 @@ fn init_heap() {
 fn panic(_info: &PanicInfo) -> ! {
     let location = _info.location().unwrap();
     let message = _info.message().unwrap();
-    println!(
+    eprintln!(
         "\nPANIC in {} at line {} \n\t{}",
         location.file(),
         location.line(),
@@ -33,10 +33,11 @@ fn panic(_info: &PanicInfo) -> ! {
     loop {}
 }
 
+// 内核将 argc 与 argv 分别放在 a0 与 a1 中
 #[no_mangle]
-pub extern "C" fn _start(_args: isize, _argv: *const u8) -> ! {
+pub extern "C" fn _start(argc: usize, argv: *const *const u8) -> ! {
     init_heap();
-    sys_exit(main())
+    sys_exit(main(argc, argv))
 }
 
 #[no_mangle]
@@ -3,6 +3,7 @@
 #![feature(lang_items)]
 #![feature(panic_info_message)]
 #![feature(linkage)]
+#![feature(naked_functions)]
 
 extern crate alloc;
 
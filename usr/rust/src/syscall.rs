@@ -1,16 +1,65 @@
 enum SyscallId {
+    Ioctl = 29,
     Open = 56,
     Close = 57,
     Pipe = 59,
+    Ppoll = 73,
     Read = 63,
     Write = 64,
     Exit = 93,
+    Nanosleep = 101,
+    GetItimer = 102,
+    SetItimer = 103,
+    ClockGettime = 113,
+    SchedSetScheduler = 119,
+    Yield = 124,
+    Kill = 129,
+    SigAction = 134,
+    SigProcMask = 135,
     SetPriority = 140,
+    GetPriority = 141,
     Time = 153,
+    SetPgid = 154,
+    GetPgid = 155,
+    GetSid = 156,
+    SetSid = 157,
+    GetRusage = 165,
+    GetTimeOfDay = 169,
+    GetPid = 172,
+    GetPpid = 173,
     Fork = 220,
     Exec = 221,
+    Wait = 260,
+    Clone = 435,
+    Lab6SetPriority = 255,
 }
 
+// 系统调用出错时返回错误码的相反数
+pub const EPERM: i64 = 1;
+pub const ENOENT: i64 = 2;
+pub const ESRCH: i64 = 3;
+pub const EINTR: i64 = 4;
+pub const EIO: i64 = 5;
+pub const E2BIG: i64 = 7;
+pub const ENOEXEC: i64 = 8;
+pub const EBADF: i64 = 9;
+pub const ECHILD: i64 = 10;
+pub const EAGAIN: i64 = 11;
+pub const ENOMEM: i64 = 12;
+pub const EFAULT: i64 = 14;
+pub const EBUSY: i64 = 16;
+pub const EEXIST: i64 = 17;
+pub const ENOTDIR: i64 = 20;
+pub const EISDIR: i64 = 21;
+pub const EINVAL: i64 = 22;
+pub const EMFILE: i64 = 24;
+pub const ENOTTY: i64 = 25;
+pub const ENOSPC: i64 = 28;
+pub const EPIPE: i64 = 32;
+pub const ENAMETOOLONG: i64 = 36;
+pub const ENOSYS: i64 = 38;
+pub const ENOTEMPTY: i64 = 39;
+
 #[inline(always)]
 fn sys_call(syscall_id: SyscallId, arg0: usize, arg1: usize, arg2: usize, arg3: usize) -> i64 {
     let id = syscall_id as usize;
@@ -52,22 +101,467 @@ pub fn sys_read(fd: usize, base: *const u8, len: usize) -> i64 {
     sys_call(SyscallId::Read, fd, base as usize, len, 0)
 }
 
-pub fn sys_exec(path: *const u8) {
-    sys_call(SyscallId::Exec, path as usize, 0, 0, 0);
+/// argv 与 envp 均为以空指针结尾的字符串数组，envp 可以为空指针
+pub fn sys_exec(path: *const u8, argv: *const *const u8, envp: *const *const u8) -> i64 {
+    sys_call(
+        SyscallId::Exec,
+        path as usize,
+        argv as usize,
+        envp as usize,
+        0,
+    )
 }
 
 pub fn sys_fork() -> i64 {
     sys_call(SyscallId::Fork, 0, 0, 0, 0)
 }
 
+pub fn sys_getpid() -> i64 {
+    sys_call(SyscallId::GetPid, 0, 0, 0, 0)
+}
+
+pub fn sys_getppid() -> i64 {
+    sys_call(SyscallId::GetPpid, 0, 0, 0, 0)
+}
+
+pub fn sys_yield() -> i64 {
+    sys_call(SyscallId::Yield, 0, 0, 0, 0)
+}
+
+/// pid 为 0 时等待任意一个子进程，返回被回收的子进程的 pid
+pub fn sys_wait(pid: usize, code: &mut i32) -> i64 {
+    sys_call(SyscallId::Wait, pid, code as *mut i32 as usize, 0, 0)
+}
+
+/// 子进程都在运行时不等待，返回 0
+pub const WNOHANG: usize = 1;
+/// 子进程停止时也返回
+pub const WUNTRACED: usize = 2;
+
+pub fn sys_waitpid(pid: usize, code: &mut i32, options: usize) -> i64 {
+    sys_call(SyscallId::Wait, pid, code as *mut i32 as usize, options, 0)
+}
+
+/// 子进程是否停止了，而不是退出
+pub fn wifstopped(code: i32) -> bool {
+    code & 0xff == 0x7f && code >> 8 != 0
+}
+
+/// 使子进程停止的信号
+pub fn wstopsig(code: i32) -> usize {
+    (code >> 8 & 0xff) as usize
+}
+
 pub fn sys_set_priority(p: usize) -> i64 {
-    sys_call(SyscallId::SetPriority, p, 0, 0, 0)
+    sys_call(SyscallId::Lab6SetPriority, p, 0, 0, 0)
+}
+
+pub const PRIO_PROCESS: usize = 0;
+
+/// who 为 0 时表示当前线程，nice 的范围为 -20 ~ 19
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> i64 {
+    sys_call(SyscallId::SetPriority, which, who, nice as usize, 0)
+}
+
+/// 成功时返回 20 - nice
+pub fn sys_getpriority(which: usize, who: usize) -> i64 {
+    sys_call(SyscallId::GetPriority, which, who, 0, 0)
+}
+
+pub fn getpriority(which: usize, who: usize) -> i64 {
+    let ret = sys_getpriority(which, who);
+    if ret < 0 {
+        ret
+    } else {
+        20 - ret
+    }
+}
+
+/// 将当前线程的 nice 值增加 inc，返回新的 nice 值
+pub fn nice(inc: isize) -> i64 {
+    let old = sys_getpriority(PRIO_PROCESS, 0);
+    if old < 0 {
+        return old;
+    }
+    let new = 20 - old + inc as i64;
+    let ret = sys_setpriority(PRIO_PROCESS, 0, new as isize);
+    if ret < 0 {
+        return ret;
+    }
+    getpriority(PRIO_PROCESS, 0)
 }
 
 pub fn set_priority(p: usize) -> i64 {
     sys_set_priority(p)
 }
 
+// 调度策略
+pub const SCHED_OTHER: usize = 0;
+pub const SCHED_FIFO: usize = 1;
+pub const SCHED_RR: usize = 2;
+pub const SCHED_DEADLINE: usize = 6;
+
+/// sched_setscheduler 的参数，时间单位为微秒
+///
+/// SCHED_FIFO 与 SCHED_RR 使用 priority（1 ~ 99），SCHED_DEADLINE 使用其余三项
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SchedParam {
+    pub priority: usize,
+    pub runtime: usize,
+    pub deadline: usize,
+    pub period: usize,
+}
+
+/// 设置当前线程（pid 为 0）的调度策略，实时线程总是先于普通线程运行
+///
+/// SCHED_DEADLINE 线程的带宽之和超过上限时返回 -EBUSY，它调用 sys_yield 表示本周期的工作已完成
+/// SCHED_OTHER 不使用 param，可以为 None
+pub fn sys_sched_setscheduler(pid: usize, policy: usize, param: Option<&SchedParam>) -> i64 {
+    sys_call(
+        SyscallId::SchedSetScheduler,
+        pid,
+        policy,
+        param.map_or(0, |param| param as *const SchedParam as usize),
+        0,
+    )
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// 睡眠 req 指定的时间，被信号打断时返回 -EINTR，rem 不为空时填入剩余的时间
+pub fn sys_nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> i64 {
+    sys_call(
+        SyscallId::Nanosleep,
+        req as *const TimeSpec as usize,
+        rem.map_or(0, |rem| rem as *mut TimeSpec as usize),
+        0,
+        0,
+    )
+}
+
+/// 睡眠 ms 毫秒，被信号打断时返回 -EINTR
+pub fn sleep_ms(ms: usize) -> i64 {
+    let req = TimeSpec {
+        sec: ms / 1000,
+        nsec: ms % 1000 * 1_000_000,
+    };
+    sys_nanosleep(&req, None)
+}
+
+// setitimer 的 which 参数，只支持按实际时间计时
+pub const ITIMER_REAL: usize = 0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ItimerVal {
+    pub interval: TimeVal,
+    pub value: TimeVal,
+}
+
+pub fn sys_getitimer(which: usize, curr: &mut ItimerVal) -> i64 {
+    sys_call(
+        SyscallId::GetItimer,
+        which,
+        curr as *mut ItimerVal as usize,
+        0,
+        0,
+    )
+}
+
+/// value 之后发送 SIGALRM，interval 不为 0 时此后每隔 interval 发送一次，value 为 0 时取消
+pub fn sys_setitimer(which: usize, new: &ItimerVal, old: Option<&mut ItimerVal>) -> i64 {
+    sys_call(
+        SyscallId::SetItimer,
+        which,
+        new as *const ItimerVal as usize,
+        old.map_or(0, |old| old as *mut ItimerVal as usize),
+        0,
+    )
+}
+
+/// sec 秒后发送 SIGALRM，为 0 时取消，返回上一次设置的剩余秒数
+pub fn alarm(sec: usize) -> usize {
+    let new = ItimerVal {
+        value: TimeVal { sec, usec: 0 },
+        ..ItimerVal::default()
+    };
+    let mut old = ItimerVal::default();
+    sys_setitimer(ITIMER_REAL, &new, Some(&mut old));
+    // 不足一秒的部分向上取整，没有到期的不会返回 0
+    old.value.sec + (old.value.usec > 0) as usize
+}
+
+// clock_gettime 的时钟，CLOCK_MONOTONIC 从开机时开始计时
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+pub fn sys_clock_gettime(clock: usize, tp: &mut TimeSpec) -> i64 {
+    sys_call(
+        SyscallId::ClockGettime,
+        clock,
+        tp as *mut TimeSpec as usize,
+        0,
+        0,
+    )
+}
+
+/// 填入 Unix 纪元以来的时间，不支持时区
+pub fn sys_gettimeofday(tv: &mut TimeVal) -> i64 {
+    sys_call(SyscallId::GetTimeOfDay, tv as *mut TimeVal as usize, 0, 0, 0)
+}
+
 pub fn sys_gettime() -> i64 {
     sys_call(SyscallId::Time, 0, 0, 0, 0)
 }
+
+/// times 的时间单位为 tick，每秒 50 个
+pub const CLOCKS_PER_SEC: i64 = 50;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Tms {
+    pub tms_utime: i64,
+    pub tms_stime: i64,
+    pub tms_cutime: i64,
+    pub tms_cstime: i64,
+}
+
+/// 返回开机以来的 tick 数，并填入当前进程与已回收的子进程使用的 CPU 时间
+pub fn sys_times(buf: &mut Tms) -> i64 {
+    sys_call(SyscallId::Time, buf as *mut Tms as usize, 0, 0, 0)
+}
+
+// getrusage 的 who 参数
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+pub const RUSAGE_THREAD: isize = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+/// 与 Linux 不同，只有内核统计的几项，另外加上被唤醒的次数
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    pub ru_nvcsw: usize,
+    pub ru_nivcsw: usize,
+    pub ru_nwakeups: usize,
+}
+
+pub fn sys_getrusage(who: isize, usage: &mut Rusage) -> i64 {
+    sys_call(
+        SyscallId::GetRusage,
+        who as usize,
+        usage as *mut Rusage as usize,
+        0,
+        0,
+    )
+}
+
+// 信号编号，与 Linux 一致
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+pub const SA_NODEFER: usize = 0x4000_0000;
+pub const SA_RESETHAND: usize = 0x8000_0000;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+pub type SigSet = u64;
+pub type SigHandler = extern "C" fn(usize);
+
+pub fn sigmask(sig: usize) -> SigSet {
+    1 << sig
+}
+
+/// sigaction 的参数，处理函数返回到 restorer，由它调用 sigreturn
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub restorer: usize,
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    /// handler 也可以是 SIG_DFL 或 SIG_IGN，执行 handler 时额外阻塞 mask 中的信号
+    pub fn new(handler: usize, mask: SigSet, flags: usize) -> Self {
+        SigAction {
+            handler,
+            flags,
+            restorer: sigreturn_trampoline as usize,
+            mask,
+        }
+    }
+}
+
+// 信号处理函数返回到这里，此时栈顶即为内核保存的信号帧，139 为 sigreturn 的系统调用号
+#[naked]
+unsafe extern "C" fn sigreturn_trampoline() {
+    asm!("li a7, 139\n ecall" :::: "volatile");
+}
+
+/// 向进程 pid 发送信号 sig，sig 为 0 时只检查进程是否存在
+///
+/// pid 为 0 时发往当前进程组，小于 -1 时发往进程组 -pid
+pub fn sys_kill(pid: isize, sig: usize) -> i64 {
+    sys_call(SyscallId::Kill, pid as usize, sig, 0, 0)
+}
+
+pub fn sys_sigaction(sig: usize, act: Option<&SigAction>, oldact: Option<&mut SigAction>) -> i64 {
+    sys_call(
+        SyscallId::SigAction,
+        sig,
+        act.map_or(0, |act| act as *const SigAction as usize),
+        oldact.map_or(0, |oldact| oldact as *mut SigAction as usize),
+        0,
+    )
+}
+
+/// 为信号 sig 注册处理函数，成功时返回 0
+pub fn signal(sig: usize, handler: SigHandler) -> i64 {
+    sys_sigaction(sig, Some(&SigAction::new(handler as usize, 0, 0)), None)
+}
+
+pub fn sys_sigprocmask(how: usize, set: Option<&SigSet>, oldset: Option<&mut SigSet>) -> i64 {
+    sys_call(
+        SyscallId::SigProcMask,
+        how,
+        set.map_or(0, |set| set as *const SigSet as usize),
+        oldset.map_or(0, |oldset| oldset as *mut SigSet as usize),
+        0,
+    )
+}
+
+// poll 的事件
+pub const POLLIN: i16 = 0x1;
+pub const POLLOUT: i16 = 0x4;
+pub const POLLERR: i16 = 0x8;
+pub const POLLHUP: i16 = 0x10;
+pub const POLLNVAL: i16 = 0x20;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+/// 等待 fds 中的文件就绪，返回就绪的个数，timeout 为空时一直等待，超时返回 0
+pub fn sys_ppoll(fds: &mut [PollFd], timeout: Option<&TimeSpec>) -> i64 {
+    sys_call(
+        SyscallId::Ppoll,
+        fds.as_mut_ptr() as usize,
+        fds.len(),
+        timeout.map_or(0, |timeout| timeout as *const TimeSpec as usize),
+        0,
+    )
+}
+
+/// 把进程 pid（为 0 时是当前进程）移入进程组 pgid（为 0 时新建以它为组长的进程组）
+pub fn sys_setpgid(pid: usize, pgid: usize) -> i64 {
+    sys_call(SyscallId::SetPgid, pid, pgid, 0, 0)
+}
+
+pub fn sys_getpgid(pid: usize) -> i64 {
+    sys_call(SyscallId::GetPgid, pid, 0, 0, 0)
+}
+
+pub fn sys_getsid(pid: usize) -> i64 {
+    sys_call(SyscallId::GetSid, pid, 0, 0, 0)
+}
+
+/// 新建以当前进程为首的会话，进程组组长调用时返回 -EPERM
+pub fn sys_setsid() -> i64 {
+    sys_call(SyscallId::SetSid, 0, 0, 0, 0)
+}
+
+// ioctl 的 request，只有控制台支持
+pub const TIOCSCTTY: usize = 0x540e;
+pub const TIOCGPGRP: usize = 0x540f;
+pub const TIOCSPGRP: usize = 0x5410;
+
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> i64 {
+    sys_call(SyscallId::Ioctl, fd, request, arg, 0)
+}
+
+/// 控制台的前台进程组，当前进程不在拥有控制台的会话中时返回 -ENOTTY
+pub fn tcgetpgrp(fd: usize) -> i64 {
+    let mut pgid: i32 = 0;
+    match sys_ioctl(fd, TIOCGPGRP, &mut pgid as *mut i32 as usize) {
+        0 => pgid as i64,
+        err => err,
+    }
+}
+
+/// 设置控制台的前台进程组，只有它收到 Ctrl-C 与 Ctrl-Z，后台进程读控制台时收到 SIGTTIN
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> i64 {
+    let pgid = pgid as i32;
+    sys_ioctl(fd, TIOCSPGRP, &pgid as *const i32 as usize)
+}
+
+/// 在当前进程中创建一个从 `entry(arg)` 开始执行、栈顶为 `ustack_top` 的线程
+pub fn sys_clone(entry: usize, ustack_top: usize, arg: usize) -> i64 {
+    sys_call(SyscallId::Clone, entry, ustack_top, arg, 0)
+}
+
+/// 创建一个线程执行 `f(arg)`，`f` 的返回值作为线程的退出码
+///
+/// 线程与当前进程共享地址空间，使用 `stack` 作为用户栈
+pub fn thread_create(f: fn(usize) -> usize, arg: usize, stack: &'static mut [u8]) -> i64 {
+    extern "C" fn thread_start(args: *const [usize; 2]) -> ! {
+        let args = unsafe { *args };
+        let f: fn(usize) -> usize = unsafe { core::mem::transmute(args[0]) };
+        sys_exit(f(args[1]))
+    }
+    // 将 f 与 arg 放在新线程的栈顶
+    let top = (stack.as_mut_ptr() as usize + stack.len()) & !0xf;
+    let args = (top - 16) as *mut [usize; 2];
+    unsafe {
+        *args = [f as usize, arg];
+    }
+    sys_clone(thread_start as usize, args as usize, args as usize)
+}
+
+/// 将以 '\0' 结尾的字符串（如 argv 中的参数）转换为 &str
+pub unsafe fn from_cstr(s: *const u8) -> &'static str {
+    use core::{slice, str};
+    let len = (0usize..).find(|&i| *s.add(i) == 0).unwrap();
+    str::from_utf8(slice::from_raw_parts(s, len)).unwrap()
+}